@@ -1,66 +1,356 @@
 use anyhow::Result;
-use std::path::PathBuf;
+use itertools::{process_results, Itertools};
+use std::{
+    borrow::Borrow,
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+    fs::File,
+    io::{BufRead, BufReader, Lines, Write},
+    path::PathBuf,
+};
 
-pub(crate) fn solve_a(path: PathBuf) -> Result<()> {
-    let file = std::fs::read_to_string(path)?;
+pub fn solve_a(path: PathBuf) -> Result<()> {
+    let reader = BufReader::new(File::open(path)?);
 
-    let mut calories: Vec<usize> = vec![];
+    if let Some(calories) = max_calories(reader)? {
+        log::info!("The most calories carried by one elf are: {calories}",);
+    } else {
+        log::error!("No elves found");
+    }
+
+    Ok(())
+}
+
+pub fn solve_b(path: PathBuf) -> Result<()> {
+    let reader = BufReader::new(File::open(path)?);
+
+    let highest_calory_count = top_n_calories(reader, 3)?;
+    log::info!("The most calories carried by three elves are: {highest_calory_count}",);
+
+    Ok(())
+}
+
+pub fn solve(path: PathBuf, top: usize, statistics: &[Statistic], percentile: f64) -> Result<()> {
+    let reader = BufReader::new(File::open(path)?);
 
-    let mut counter = 0;
-    for line in file.lines() {
-        if line.is_empty() {
-            log::trace!("Elf has {counter} calories");
+    let elves = parse_elves(reader)?;
+    log::debug!("Elves: {elves:?}");
 
-            calories.push(counter);
-            counter = 0;
-        } else {
-            let number = line.parse::<usize>()?;
-            counter += number;
+    let highest_calory_count: usize = top_n(&elves, top).iter().map(|elf| elf.calories()).sum();
+    log::info!("The most calories carried by {top} elves are: {highest_calory_count}");
+
+    for statistic in statistics {
+        match statistic {
+            Statistic::Mean => log::info!("Mean calories: {:?}", mean(&elves)),
+            Statistic::Median => log::info!("Median calories: {:?}", median(&elves)),
+            Statistic::Percentile => log::info!(
+                "{percentile}th percentile of calories: {:?}",
+                calories_percentile(&elves, percentile)?
+            ),
+            Statistic::MaxIndex => {
+                log::info!(
+                    "Index of the elf carrying the most: {:?}",
+                    max_index(&elves)
+                )
+            }
         }
     }
-    log::trace!("Elf has {counter} calories");
-    calories.push(counter);
 
-    log::debug!("Calories vec: {calories:?}");
+    Ok(())
+}
 
-    if let Some(calories) = calories
-        .into_iter()
-        .reduce(|acc, value| if acc >= value { acc } else { value })
-    {
-        log::info!("The most calories carried by one elf are: {calories}",);
-    } else {
-        log::error!("No elves found");
+pub fn solve_report(path: PathBuf, top: usize, format: ReportFormat) -> Result<()> {
+    let reader = BufReader::new(File::open(path)?);
+
+    let top_elves = process_results(elves(reader), |elves| top_n(elves, top))?;
+    log::debug!("Top elves: {top_elves:?}");
+
+    write_report(std::io::stdout().lock(), &top_elves, format)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Elf {
+    /// Position of the elf within the input, starting at 0
+    pub index: usize,
+    pub items: Vec<usize>,
+}
+
+impl Elf {
+    pub fn calories(&self) -> usize {
+        self.items.iter().sum()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Statistic {
+    Mean,
+    Median,
+    Percentile,
+    MaxIndex,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ReportFormat {
+    Text,
+    Csv,
+}
+
+/// Writes one entry per elf, in the given order, listing its rank, its number within the
+/// input starting at 1, its items and their total.
+pub fn write_report<W: Write>(mut writer: W, elves: &[Elf], format: ReportFormat) -> Result<()> {
+    if format == ReportFormat::Csv {
+        writeln!(writer, "rank,elf,items,total")?;
+    }
+
+    for (rank, elf) in elves.iter().enumerate() {
+        let rank = rank + 1;
+        let total = elf.calories();
+
+        match format {
+            ReportFormat::Text => writeln!(
+                writer,
+                "#{rank} elf {}: {} = {total}",
+                elf.index + 1,
+                elf.items.iter().join(" + ")
+            )?,
+            ReportFormat::Csv => writeln!(
+                writer,
+                "{rank},{},{},{total}",
+                elf.index + 1,
+                elf.items.iter().join(";")
+            )?,
+        }
     }
 
     Ok(())
 }
 
-pub(crate) fn solve_b(path: PathBuf) -> Result<()> {
-    let file = std::fs::read_to_string(path)?;
+/// Returns the most calories carried by a single elf.
+pub fn max_calories<R: BufRead>(reader: R) -> Result<Option<usize>> {
+    let top = process_results(elves(reader), |elves| top_n(elves, 1))?;
 
-    let mut calories: Vec<usize> = vec![];
+    Ok(top.first().map(Elf::calories))
+}
+
+/// Returns the sum of the calories carried by the `n` elves carrying the most, keeping
+/// only those `n` elves in memory.
+pub fn top_n_calories<R: BufRead>(reader: R, n: usize) -> Result<usize> {
+    let top = process_results(elves(reader), |elves| top_n(elves, n))?;
+
+    Ok(top.iter().map(Elf::calories).sum())
+}
 
-    let mut counter = 0;
-    for line in file.lines() {
-        if line.is_empty() {
-            log::trace!("Elf has {counter} calories");
+/// Returns the `n` elves carrying the most calories in descending order, elves appearing
+/// earlier in the input winning ties.
+pub fn top_n<E: Borrow<Elf>>(elves: impl IntoIterator<Item = E>, n: usize) -> Vec<E> {
+    let mut heap = BinaryHeap::with_capacity(n + 1);
 
-            calories.push(counter);
-            counter = 0;
-        } else {
-            let number = line.parse::<usize>()?;
-            counter += number;
+    for elf in elves {
+        heap.push(Reverse(RankedElf(elf)));
+
+        if heap.len() > n {
+            heap.pop();
         }
     }
-    log::trace!("Elf has {counter} calories");
-    calories.push(counter);
 
-    log::debug!("Calories vec: {calories:?}");
-    calories.sort_by(|a, b| b.cmp(a));
-    log::debug!("Calories vec sorted: {calories:?}");
+    heap.into_sorted_vec()
+        .into_iter()
+        .map(|Reverse(elf)| elf.0)
+        .collect()
+}
 
-    let highest_calory_count: usize = calories[0..3].iter().sum();
-    log::info!("The most calories carried by three elves are: {highest_calory_count}",);
+/// Orders elves by their calories, ranking earlier elves higher on ties.
+struct RankedElf<E>(E);
 
-    Ok(())
+impl<E: Borrow<Elf>> Ord for RankedElf<E> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (this, other) = (self.0.borrow(), other.0.borrow());
+
+        this.calories()
+            .cmp(&other.calories())
+            .then_with(|| other.index.cmp(&this.index))
+    }
+}
+
+impl<E: Borrow<Elf>> PartialOrd for RankedElf<E> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<E: Borrow<Elf>> PartialEq for RankedElf<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<E: Borrow<Elf>> Eq for RankedElf<E> {}
+
+pub fn mean(elves: &[Elf]) -> Option<f64> {
+    if elves.is_empty() {
+        return None;
+    }
+
+    let total: usize = elves.iter().map(Elf::calories).sum();
+
+    Some(total as f64 / elves.len() as f64)
+}
+
+pub fn median(elves: &[Elf]) -> Option<f64> {
+    let calories = sorted_calories(elves);
+
+    match calories.len() {
+        0 => None,
+        len if len % 2 == 0 => Some((calories[len / 2 - 1] + calories[len / 2]) as f64 / 2.0),
+        len => Some(calories[len / 2] as f64),
+    }
+}
+
+/// Nearest-rank percentile of the calories carried, `percentile` ranging from 0 to 100.
+pub fn calories_percentile(elves: &[Elf], percentile: f64) -> Result<Option<usize>> {
+    if !(0.0..=100.0).contains(&percentile) {
+        anyhow::bail!("Percentile {percentile} is not within 0 and 100");
+    }
+
+    let calories = sorted_calories(elves);
+    let rank = (percentile / 100.0 * calories.len() as f64).ceil() as usize;
+
+    Ok(calories.get(rank.max(1) - 1).copied())
+}
+
+/// Index of the elf carrying the most calories, the first one winning ties.
+pub fn max_index(elves: &[Elf]) -> Option<usize> {
+    top_n(elves, 1).first().map(|elf| elf.index)
+}
+
+fn sorted_calories(elves: &[Elf]) -> Vec<usize> {
+    let mut calories: Vec<usize> = elves.iter().map(Elf::calories).collect();
+    calories.sort_unstable();
+
+    calories
+}
+
+pub fn parse_elves<R: BufRead>(reader: R) -> Result<Vec<Elf>> {
+    elves(reader).collect()
+}
+
+/// Streams the elves and the items they carry without holding the input in memory.
+pub fn elves<R: BufRead>(reader: R) -> Elves<R> {
+    Elves {
+        lines: reader.lines(),
+        index: 0,
+        done: false,
+    }
+}
+
+pub struct Elves<R> {
+    lines: Lines<R>,
+    index: usize,
+    done: bool,
+}
+
+impl<R: BufRead> Iterator for Elves<R> {
+    type Item = Result<Elf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut items = vec![];
+        loop {
+            match self.lines.next() {
+                Some(Ok(line)) if line.is_empty() => break,
+                Some(Ok(line)) => match line.parse::<usize>() {
+                    Ok(number) => items.push(number),
+                    Err(err) => return Some(Err(err.into())),
+                },
+                Some(Err(err)) => return Some(Err(err.into())),
+                None => {
+                    self.done = true;
+                    break;
+                }
+            }
+        }
+
+        let elf = Elf {
+            index: self.index,
+            items,
+        };
+        self.index += 1;
+        log::trace!("Elf {} has {} calories", elf.index, elf.calories());
+
+        Some(Ok(elf))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_FILE: &str = include_str!("../tasks/day1_dev.txt");
+
+    #[test]
+    fn test_elves() {
+        let elves = parse_elves(TEST_FILE.as_bytes());
+
+        assert!(elves.is_ok());
+        assert_eq!(
+            elves.unwrap().iter().map(Elf::calories).collect::<Vec<_>>(),
+            vec![6000, 4000, 11000, 24000, 10000]
+        );
+    }
+
+    #[test]
+    fn test_streaming_solutions() {
+        assert_eq!(max_calories(TEST_FILE.as_bytes()).unwrap(), Some(24000));
+        assert_eq!(top_n_calories(TEST_FILE.as_bytes(), 3).unwrap(), 45000);
+        assert_eq!(top_n_calories("100\n\n200".as_bytes(), 3).unwrap(), 300);
+    }
+
+    #[test]
+    fn test_top_n() {
+        let elves = parse_elves(TEST_FILE.as_bytes()).unwrap();
+
+        let top = top_n(&elves, 3);
+        assert_eq!(
+            top.iter().map(|elf| elf.index).collect::<Vec<_>>(),
+            vec![3, 2, 4]
+        );
+        assert_eq!(top_n(&elves, 10).len(), 5);
+        assert!(top_n(&elves, 0).is_empty());
+    }
+
+    #[test]
+    fn test_write_report() {
+        let elves = parse_elves(TEST_FILE.as_bytes()).unwrap();
+        let top = top_n(elves, 2);
+
+        let mut text = vec![];
+        assert!(write_report(&mut text, &top, ReportFormat::Text).is_ok());
+        assert_eq!(
+            String::from_utf8(text).unwrap(),
+            "#1 elf 4: 7000 + 8000 + 9000 = 24000\n#2 elf 3: 5000 + 6000 = 11000\n"
+        );
+
+        let mut csv = vec![];
+        assert!(write_report(&mut csv, &top, ReportFormat::Csv).is_ok());
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "rank,elf,items,total\n1,4,7000;8000;9000,24000\n2,3,5000;6000,11000\n"
+        );
+    }
+
+    #[test]
+    fn test_statistics() {
+        let elves = parse_elves(TEST_FILE.as_bytes()).unwrap();
+
+        assert_eq!(mean(&elves), Some(11000.0));
+        assert_eq!(median(&elves), Some(10000.0));
+        assert_eq!(calories_percentile(&elves, 100.0).unwrap(), Some(24000));
+        assert_eq!(calories_percentile(&elves, 20.0).unwrap(), Some(4000));
+        assert!(calories_percentile(&elves, 101.0).is_err());
+        assert_eq!(max_index(&elves), Some(3));
+        assert_eq!(max_index(&[]), None);
+    }
 }
@@ -3,7 +3,7 @@ use std::path::PathBuf;
 use anyhow::Result;
 use chumsky::prelude::*;
 
-pub(crate) fn solve(path: PathBuf) -> Result<()> {
+pub fn solve(path: PathBuf) -> Result<()> {
     let file = std::fs::read_to_string(path)?;
 
     let mut parsed_instructions = file_parser()
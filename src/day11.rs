@@ -3,7 +3,7 @@ use std::path::PathBuf;
 use anyhow::Result;
 use chumsky::prelude::*;
 
-pub(crate) fn solve(path: PathBuf, rounds: u32, decreasing_worry_levels: bool) -> Result<()> {
+pub fn solve(path: PathBuf, rounds: u32, decreasing_worry_levels: bool) -> Result<()> {
     let file = std::fs::read_to_string(path)?;
 
     let monkeys = file_parser()
@@ -119,7 +119,6 @@ fn monkey_parser() -> impl Parser<char, Monkey, Error = Simple<char>> {
                 .into_iter()
                 .collect::<String>()
                 .split(',')
-                .into_iter()
                 .map(|item| item.trim().parse::<u32>().unwrap())
                 .collect::<Vec<u32>>()
         }))
@@ -193,7 +192,7 @@ fn monkey_parser() -> impl Parser<char, Monkey, Error = Simple<char>> {
                     (
                         modulo,
                         Box::new(move |value: u32| {
-                            if value % modulo == 0 {
+                            if value.is_multiple_of(modulo) {
                                 true_condition
                             } else {
                                 false_condition
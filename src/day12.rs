@@ -4,7 +4,7 @@ use anyhow::Result;
 
 type Coordinate = (usize, usize);
 
-pub(crate) fn solve(path: PathBuf) -> Result<()> {
+pub fn solve(path: PathBuf) -> Result<()> {
     let file = std::fs::read_to_string(path)?;
 
     let heights: Vec<Vec<u8>> = file
@@ -69,7 +69,7 @@ pub(crate) fn solve(path: PathBuf) -> Result<()> {
     Ok(())
 }
 
-fn possible_coordinates(heights: &Vec<Vec<u8>>, current_position: Coordinate) -> Vec<Coordinate> {
+fn possible_coordinates(heights: &[Vec<u8>], current_position: Coordinate) -> Vec<Coordinate> {
     let mut possible_moves = vec![];
 
     let (x, y) = current_position;
@@ -98,7 +98,7 @@ fn possible_coordinates(heights: &Vec<Vec<u8>>, current_position: Coordinate) ->
     possible_moves
 }
 
-fn bfs(heights: &Vec<Vec<u8>>, start: Coordinate, end: Coordinate) -> Option<u32> {
+fn bfs(heights: &[Vec<u8>], start: Coordinate, end: Coordinate) -> Option<u32> {
     let mut counter = 0;
     let mut visited: HashSet<Coordinate> = HashSet::new();
     let mut next_moves: HashSet<Coordinate> = HashSet::new();
@@ -4,7 +4,7 @@ use anyhow::Result;
 use chumsky::prelude::*;
 use itertools::Itertools;
 
-pub(crate) fn solve(path: PathBuf) -> Result<()> {
+pub fn solve(path: PathBuf) -> Result<()> {
     let file = std::fs::read_to_string(path)?;
 
     let parsed_file = file_parser().parse(file).map_err(|err| {
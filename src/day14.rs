@@ -11,7 +11,7 @@ enum Entry {
     // Tilde,
 }
 
-pub(crate) fn solve(path: PathBuf) -> Result<()> {
+pub fn solve(path: PathBuf) -> Result<()> {
     let file = std::fs::read_to_string(path)?;
 
     let parsed_file = file_parser()
@@ -254,7 +254,6 @@ fn line_parser() -> impl Parser<char, Vec<(Coordinate, Coordinate)>, Error = Sim
         .map(|coordinates| {
             coordinates
                 .windows(2)
-                .into_iter()
                 .map(|coordinate| (coordinate[0], coordinate[1]))
                 .collect::<Vec<_>>()
         })
@@ -5,7 +5,7 @@ use chumsky::prelude::*;
 use itertools::Itertools;
 use rayon::prelude::*;
 
-pub(crate) fn solve(path: PathBuf, y: i32, xy_limit: i32) -> Result<()> {
+pub fn solve(path: PathBuf, y: i32, xy_limit: i32) -> Result<()> {
     let file = std::fs::read_to_string(path)?;
 
     let parsed_file = file_parser()
@@ -71,9 +71,7 @@ pub(crate) fn solve(path: PathBuf, y: i32, xy_limit: i32) -> Result<()> {
         })
         .filter_map(|(y, ranges)| {
             let mut ranges = ranges;
-            let Some(range) = ranges.next() else {
-                return None;
-            };
+            let range = ranges.next()?;
 
             let mut min = range.0;
             let mut max = range.1;
@@ -6,7 +6,7 @@ use itertools::Itertools;
 use petgraph::{algo::floyd_warshall, prelude::*};
 use rayon::prelude::*;
 
-pub(crate) fn solve(path: PathBuf) -> Result<()> {
+pub fn solve(path: PathBuf) -> Result<()> {
     let file = std::fs::read_to_string(path)?;
 
     let parsed_file = file_parser()
@@ -38,7 +38,7 @@ pub(crate) fn solve(path: PathBuf) -> Result<()> {
             next_node_ids
                 .iter()
                 .filter_map(|next_node_id| {
-                    let Some(next_node) = nodes.get(&**next_node_id) else {return None};
+                    let next_node = nodes.get(&**next_node_id)?;
 
                     Some((*current_node, *next_node))
                 })
@@ -194,7 +194,7 @@ struct Path {
     paths: Vec<Path>,
 }
 
-fn find_highest_rated_path(paths: &Vec<Path>) -> Option<(i32, String)> {
+fn find_highest_rated_path(paths: &[Path]) -> Option<(i32, String)> {
     if paths.is_empty() {
         return None;
     }
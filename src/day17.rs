@@ -3,7 +3,7 @@ use std::path::PathBuf;
 use anyhow::Result;
 use chumsky::prelude::*;
 
-pub(crate) fn solve(path: PathBuf, rock_count: usize) -> Result<()> {
+pub fn solve(path: PathBuf, rock_count: usize) -> Result<()> {
     let file = std::fs::read_to_string(path)?;
 
     let parsed_file = file_parser()
@@ -6,7 +6,7 @@ use std::{
 
 use anyhow::Result;
 
-pub(crate) fn solve(path: PathBuf) -> Result<()> {
+pub fn solve(path: PathBuf) -> Result<()> {
     let file = std::fs::read_to_string(path)?;
 
     let cubes = file_parser(&file);
@@ -70,13 +70,13 @@ pub(crate) fn solve(path: PathBuf) -> Result<()> {
 
     while let Some((x, y, z)) = queue.pop_front() {
         if !block_grid[z][y][x] && !reachable_coordinates[z][y][x] {
-            if x + 1 <= max_x {
+            if x < max_x {
                 queue.push_back((x + 1, y, z));
             }
-            if y + 1 <= max_y {
+            if y < max_y {
                 queue.push_back((x, y + 1, z));
             }
-            if z + 1 <= max_z {
+            if z < max_z {
                 queue.push_back((x, y, z + 1));
             }
         }
@@ -90,11 +90,9 @@ pub(crate) fn solve(path: PathBuf) -> Result<()> {
         .into_iter()
         .enumerate()
         .flat_map(|(z, vec)| {
-            let z = z.clone();
             let block_grid = block_grid.clone();
 
             vec.into_iter().enumerate().flat_map(move |(y, vec)| {
-                let y = y.clone();
                 let block_grid = block_grid.clone();
 
                 vec.into_iter().enumerate().map(move |(x, value)| {
@@ -104,21 +102,21 @@ pub(crate) fn solve(path: PathBuf) -> Result<()> {
                         if x > 0 && block_grid[z][y][x - 1] {
                             side_count += 1;
                         }
-                        if x + 1 <= max_x && block_grid[z][y][x + 1] {
+                        if x < max_x && block_grid[z][y][x + 1] {
                             side_count += 1;
                         }
 
                         if y > 0 && block_grid[z][y - 1][x] {
                             side_count += 1;
                         }
-                        if y + 1 <= max_y && block_grid[z][y + 1][x] {
+                        if y < max_y && block_grid[z][y + 1][x] {
                             side_count += 1;
                         }
 
                         if z > 0 && block_grid[z - 1][y][x] {
                             side_count += 1;
                         }
-                        if z + 1 <= max_z && block_grid[z + 1][y][x] {
+                        if z < max_z && block_grid[z + 1][y][x] {
                             side_count += 1;
                         }
 
@@ -1,113 +1,537 @@
-use std::path::PathBuf;
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::PathBuf,
+    str::FromStr,
+};
 
 use anyhow::Result;
+use chumsky::prelude::*;
+use itertools::Itertools;
 
-#[derive(Debug, PartialEq)]
-pub(crate) enum Choice {
-    Rock,
-    Paper,
-    Scissors,
+/// A shape, identified by its position within the cycle of shapes of the [`Rules`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Choice(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Loss,
+    Draw,
+    Win,
 }
 
-impl TryFrom<&str> for Choice {
-    type Error = anyhow::Error;
+impl FromStr for Outcome {
+    type Err = anyhow::Error;
 
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
         match value {
-            "A" => Ok(Self::Rock),
-            "B" => Ok(Self::Paper),
-            "C" => Ok(Self::Scissors),
+            "loss" => Ok(Self::Loss),
+            "draw" => Ok(Self::Draw),
+            "win" => Ok(Self::Win),
+            _ => Err(anyhow::anyhow!("Could not determine outcome {value}")),
+        }
+    }
+}
 
-            "X" => Ok(Self::Rock),
-            "Y" => Ok(Self::Paper),
-            "Z" => Ok(Self::Scissors),
+impl Choice {
+    /// Returns the outcome score of this choice played against the opponent's choice.
+    pub fn get_score(&self, opponent: &Self, rules: &Rules) -> usize {
+        rules.outcome_score(rules.outcome(self, opponent))
+    }
+}
 
-            _ => Err(anyhow::anyhow!("Could not determine choice")),
-        }
+/// Rules of a game in which every shape beats half of the other shapes, e.g.
+/// Rock-Paper-Scissors or Rock-Paper-Scissors-Lizard-Spock.
+///
+/// The shapes form a cycle in which each shape beats the shapes an odd number of steps
+/// before it and loses against the shapes an even number of steps before it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rules {
+    shapes: Vec<(String, usize)>,
+    opponent_symbols: HashMap<String, Choice>,
+    response_symbols: HashMap<String, Choice>,
+    outcome_symbols: HashMap<String, Outcome>,
+    outcome_scores: HashMap<Outcome, usize>,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        include_str!("../tasks/day2_rules.txt")
+            .parse()
+            .expect("Default rules are valid")
     }
 }
 
-impl TryFrom<(&Choice, &str)> for Choice {
-    type Error = anyhow::Error;
+impl Rules {
+    pub fn shape_count(&self) -> usize {
+        self.shapes.len()
+    }
 
-    fn try_from((opponent, outcome): (&Choice, &str)) -> Result<Self, Self::Error> {
-        match (opponent, outcome) {
-            (Choice::Rock, "X") => Ok(Self::Scissors),
-            (Choice::Paper, "X") => Ok(Self::Rock),
-            (Choice::Scissors, "X") => Ok(Self::Paper),
+    pub fn shape_name(&self, choice: &Choice) -> &str {
+        &self.shapes[choice.0].0
+    }
 
-            (Choice::Rock, "Y") => Ok(Self::Rock),
-            (Choice::Paper, "Y") => Ok(Self::Paper),
-            (Choice::Scissors, "Y") => Ok(Self::Scissors),
+    pub fn shape_score(&self, choice: &Choice) -> usize {
+        self.shapes[choice.0].1
+    }
+
+    pub fn outcome_score(&self, outcome: Outcome) -> usize {
+        self.outcome_scores[&outcome]
+    }
 
-            (Choice::Rock, "Z") => Ok(Self::Paper),
-            (Choice::Paper, "Z") => Ok(Self::Scissors),
-            (Choice::Scissors, "Z") => Ok(Self::Rock),
+    pub fn outcome(&self, choice: &Choice, opponent: &Choice) -> Outcome {
+        let distance = (choice.0 + self.shapes.len() - opponent.0) % self.shapes.len();
 
-            _ => Err(anyhow::anyhow!("Could not determine choice")),
+        match distance {
+            0 => Outcome::Draw,
+            distance if distance % 2 == 1 => Outcome::Win,
+            _ => Outcome::Loss,
         }
     }
+
+    /// Returns the choice leading to the desired outcome, preferring the highest scoring
+    /// shape if several shapes lead to it.
+    pub fn choice_for_outcome(&self, opponent: &Choice, outcome: Outcome) -> Choice {
+        (0..self.shapes.len())
+            .map(Choice)
+            .filter(|choice| self.outcome(choice, opponent) == outcome)
+            .max_by_key(|choice| self.shape_score(choice))
+            .unwrap_or(*opponent)
+    }
+
+    /// Symbols a response can be written as, in alphabetical order.
+    pub fn response_symbols(&self) -> Vec<&str> {
+        self.response_symbols
+            .keys()
+            .map(String::as_str)
+            .sorted()
+            .collect()
+    }
+
+    pub fn opponent_choice(&self, symbol: &str) -> Result<Choice> {
+        self.opponent_symbols
+            .get(symbol)
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("Could not determine choice for {symbol}"))
+    }
+
+    pub fn response_choice(&self, symbol: &str) -> Result<Choice> {
+        self.response_symbols
+            .get(symbol)
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("Could not determine choice for {symbol}"))
+    }
+
+    pub fn response_outcome(&self, symbol: &str) -> Result<Outcome> {
+        self.outcome_symbols
+            .get(symbol)
+            .copied()
+            .ok_or_else(|| anyhow::anyhow!("Could not determine outcome for {symbol}"))
+    }
 }
 
-impl From<&Choice> for usize {
-    fn from(choice: &Choice) -> Self {
-        match choice {
-            Choice::Rock => 1,
-            Choice::Paper => 2,
-            Choice::Scissors => 3,
+impl FromStr for Rules {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let sections: HashMap<_, _> = rules_parser()
+            .parse(value)
+            .map_err(|err| anyhow::anyhow!("An error occurred while parsing the rules: {err:?}"))?
+            .into_iter()
+            .collect();
+
+        let section = |name: &str| {
+            sections
+                .get(name)
+                .ok_or_else(|| anyhow::anyhow!("Rules are missing the {name} section"))
+        };
+
+        let shapes = section("shapes")?
+            .iter()
+            .map(|(name, score)| Ok((name.clone(), score.parse()?)))
+            .collect::<Result<Vec<(String, usize)>>>()?;
+        if shapes.len() % 2 == 0 {
+            anyhow::bail!("An odd amount of shapes is required, got {}", shapes.len());
         }
+
+        let shape_symbols = |name: &str| {
+            section(name)?
+                .iter()
+                .map(|(symbol, shape)| {
+                    let index = shapes
+                        .iter()
+                        .position(|(name, _)| name == shape)
+                        .ok_or_else(|| anyhow::anyhow!("Unknown shape {shape}"))?;
+
+                    Ok((symbol.clone(), Choice(index)))
+                })
+                .collect::<Result<HashMap<_, _>>>()
+        };
+        let opponent_symbols = shape_symbols("opponent")?;
+        let response_symbols = shape_symbols("response")?;
+
+        let outcome_symbols = section("outcomes")?
+            .iter()
+            .map(|(symbol, outcome)| Ok((symbol.clone(), outcome.parse()?)))
+            .collect::<Result<HashMap<_, _>>>()?;
+
+        let outcome_scores = section("scores")?
+            .iter()
+            .map(|(outcome, score)| Ok((outcome.parse()?, score.parse()?)))
+            .collect::<Result<HashMap<_, _>>>()?;
+        for outcome in [Outcome::Loss, Outcome::Draw, Outcome::Win] {
+            if !outcome_scores.contains_key(&outcome) {
+                anyhow::bail!("Rules are missing a score for {outcome:?}");
+            }
+        }
+
+        Ok(Self {
+            shapes,
+            opponent_symbols,
+            response_symbols,
+            outcome_symbols,
+            outcome_scores,
+        })
     }
 }
 
-impl Choice {
-    fn get_score(&self, opponent: &Self) -> usize {
-        match (self, opponent) {
-            (Choice::Rock, Choice::Rock) => 3,
-            (Choice::Rock, Choice::Paper) => 0,
-            (Choice::Rock, Choice::Scissors) => 6,
-            (Choice::Paper, Choice::Rock) => 6,
-            (Choice::Paper, Choice::Paper) => 3,
-            (Choice::Paper, Choice::Scissors) => 0,
-            (Choice::Scissors, Choice::Rock) => 0,
-            (Choice::Scissors, Choice::Paper) => 6,
-            (Choice::Scissors, Choice::Scissors) => 3,
-        }
+pub fn solve(path: PathBuf, part_2: bool, rules: Option<PathBuf>) -> Result<()> {
+    let rules = match rules {
+        Some(rules) => std::fs::read_to_string(rules)?.parse()?,
+        None => Rules::default(),
+    };
+    log::debug!("Rules: {rules:?}");
+
+    let reader = BufReader::new(File::open(path)?);
+
+    let total_score = total_score(reader, part_2, &rules)?;
+    log::info!("Total score: {total_score}");
+
+    Ok(())
+}
+
+/// Sums up the score of every round in the strategy guide, reading it line by line.
+pub fn total_score<R: BufRead>(reader: R, part_2: bool, rules: &Rules) -> Result<usize> {
+    reader.lines().try_fold(0, |total_score, line| {
+        let line = line?;
+        log::trace!("Line: {line}");
+
+        let (first, second) = split_round(&line)?;
+        log::trace!("First {first}, second {second}");
+
+        let first = rules.opponent_choice(first)?;
+        let second = if !part_2 {
+            rules.response_choice(second)?
+        } else {
+            rules.choice_for_outcome(&first, rules.response_outcome(second)?)
+        };
+        log::debug!(
+            "First {}, Second {}",
+            rules.shape_name(&first),
+            rules.shape_name(&second)
+        );
+
+        let round_outcome = &second.get_score(&first, rules);
+        log::debug!("Round outcome {}", round_outcome);
+
+        let round_score: usize = rules.shape_score(&second) + round_outcome;
+        log::debug!("Round score: {round_score}");
+
+        Ok(total_score + round_score)
+    })
+}
+
+pub fn solve_analysis(path: PathBuf, rules: Option<PathBuf>) -> Result<()> {
+    let rules = match rules {
+        Some(rules) => std::fs::read_to_string(rules)?.parse()?,
+        None => Rules::default(),
+    };
+
+    let reader = BufReader::new(File::open(path)?);
+    let guide = parse_guide(reader, &rules)?;
+
+    let breakdown = guide_breakdown(&guide, &rules)?;
+    log::info!("Guide as given: {breakdown:?}");
+
+    let optimal = optimal_breakdown(guide.iter().map(|(opponent, _)| opponent), &rules);
+    log::info!("Maximum achievable: {optimal:?}");
+
+    let mappings = mapping_breakdowns(&guide, &rules);
+    for (mapping, breakdown) in &mappings {
+        log::info!("{}: {breakdown:?}", format_mapping(mapping, &rules));
+    }
+
+    if let Some((mapping, breakdown)) = mappings.first() {
+        log::info!(
+            "Most likely intended mapping: {} with a score of {}",
+            format_mapping(mapping, &rules),
+            breakdown.score
+        );
     }
+
+    Ok(())
+}
+
+/// Wins, draws, losses and total score of a sequence of rounds.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Breakdown {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    pub score: usize,
 }
 
-pub(crate) fn solve(path: PathBuf, part_2: bool) -> Result<()> {
-    let file = std::fs::read_to_string(path)?;
+impl Breakdown {
+    pub fn record(&mut self, choice: &Choice, opponent: &Choice, rules: &Rules) {
+        match rules.outcome(choice, opponent) {
+            Outcome::Win => self.wins += 1,
+            Outcome::Draw => self.draws += 1,
+            Outcome::Loss => self.losses += 1,
+        }
+
+        self.score += rules.shape_score(choice) + choice.get_score(opponent, rules);
+    }
+}
 
-    let total_score: usize = file
+/// Parses the strategy guide into the opponent's choice and the unresolved response
+/// symbol of each round.
+pub fn parse_guide<R: BufRead>(reader: R, rules: &Rules) -> Result<Vec<(Choice, String)>> {
+    reader
         .lines()
         .map(|line| {
-            log::trace!("Line: {line}");
+            let line = line?;
+            let (first, second) = split_round(&line)?;
 
-            let mut split = line.split_ascii_whitespace();
-            let first = split.next().expect("Could not get first character");
-            let second = split.next().expect("Could not get second character");
+            Ok((rules.opponent_choice(first)?, second.to_owned()))
+        })
+        .collect()
+}
 
-            log::trace!("First {first}, second {second}");
+/// Breakdown of the guide when reading its response symbols as shapes.
+pub fn guide_breakdown(guide: &[(Choice, String)], rules: &Rules) -> Result<Breakdown> {
+    guide
+        .iter()
+        .try_fold(Breakdown::default(), |mut breakdown, (opponent, symbol)| {
+            breakdown.record(&rules.response_choice(symbol)?, opponent, rules);
 
-            let first = Choice::try_from(first).unwrap();
-            let second = if !part_2 {
-                Choice::try_from(second).unwrap()
-            } else {
-                Choice::try_from((&first, second)).unwrap()
-            };
-            log::debug!("First {first:?}, Second {second:?}");
+            Ok(breakdown)
+        })
+}
 
-            let round_outcome = &second.get_score(&first);
-            log::debug!("Round outcome {}", round_outcome);
+/// Breakdown when answering every opponent choice with the highest scoring response.
+pub fn optimal_breakdown<'a>(
+    opponents: impl IntoIterator<Item = &'a Choice>,
+    rules: &Rules,
+) -> Breakdown {
+    opponents
+        .into_iter()
+        .fold(Breakdown::default(), |mut breakdown, opponent| {
+            let best_choice = (0..rules.shape_count())
+                .map(Choice)
+                .max_by_key(|choice| rules.shape_score(choice) + choice.get_score(opponent, rules))
+                .unwrap_or(*opponent);
 
-            let round_score: usize = usize::from(&second) + round_outcome;
-            log::debug!("Round score: {round_score}");
+            breakdown.record(&best_choice, opponent, rules);
 
-            round_score
+            breakdown
         })
-        .sum();
+}
 
-    log::info!("Total score: {total_score}");
+/// Tries every assignment of the response symbols to distinct shapes, returning the
+/// breakdown of each, the highest scoring and thus most likely intended one first.
+pub fn mapping_breakdowns(
+    guide: &[(Choice, String)],
+    rules: &Rules,
+) -> Vec<(Vec<(String, Choice)>, Breakdown)> {
+    let symbols = rules.response_symbols();
 
-    Ok(())
+    let mut breakdowns: Vec<_> = (0..rules.shape_count())
+        .map(Choice)
+        .permutations(symbols.len())
+        .map(|choices| {
+            let mapping: HashMap<&str, Choice> = symbols
+                .iter()
+                .copied()
+                .zip(choices.iter().copied())
+                .collect();
+
+            let breakdown =
+                guide
+                    .iter()
+                    .fold(Breakdown::default(), |mut breakdown, (opponent, symbol)| {
+                        if let Some(choice) = mapping.get(symbol.as_str()) {
+                            breakdown.record(choice, opponent, rules);
+                        }
+
+                        breakdown
+                    });
+
+            let mapping = symbols
+                .iter()
+                .map(|symbol| symbol.to_string())
+                .zip(choices)
+                .collect();
+
+            (mapping, breakdown)
+        })
+        .collect();
+
+    breakdowns.sort_by_key(|(_, breakdown)| Reverse(breakdown.score));
+
+    breakdowns
+}
+
+fn format_mapping(mapping: &[(String, Choice)], rules: &Rules) -> String {
+    mapping
+        .iter()
+        .map(|(symbol, choice)| format!("{symbol}={}", rules.shape_name(choice)))
+        .join(" ")
+}
+
+fn split_round(line: &str) -> Result<(&str, &str)> {
+    let mut split = line.split_ascii_whitespace();
+    let first = split
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Could not get first character"))?;
+    let second = split
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("Could not get second character"))?;
+
+    Ok((first, second))
+}
+
+// --- Parser ---
+
+type RulesSection = (String, Vec<(String, String)>);
+
+fn rules_parser() -> impl Parser<char, Vec<RulesSection>, Error = Simple<char>> {
+    text::ident()
+        .then(symbol_pair_parser().repeated().at_least(1))
+        .padded()
+        .repeated()
+        .then_ignore(end())
+}
+
+fn symbol_pair_parser() -> impl Parser<char, (String, String), Error = Simple<char>> {
+    word_parser()
+        .padded()
+        .then_ignore(just('='))
+        .then(word_parser())
+        .labelled("symbol pair")
+}
+
+fn word_parser() -> impl Parser<char, String, Error = Simple<char>> {
+    filter(|c: &char| c.is_alphanumeric())
+        .repeated()
+        .at_least(1)
+        .collect()
+}
+
+// --- Tests ---
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_FILE: &str = include_str!("../tasks/day2_dev.txt");
+
+    #[test]
+    fn test_total_score() {
+        let rules = Rules::default();
+
+        assert_eq!(
+            total_score(TEST_FILE.as_bytes(), false, &rules).unwrap(),
+            15
+        );
+        assert_eq!(total_score(TEST_FILE.as_bytes(), true, &rules).unwrap(), 12);
+    }
+
+    #[test]
+    fn test_symbol_pair_parsing() {
+        let res = symbol_pair_parser().parse("A=Rock");
+
+        assert!(res.is_ok());
+        assert_eq!(res.unwrap(), ("A".to_owned(), "Rock".to_owned()));
+    }
+
+    #[test]
+    fn test_rpsls_rules() {
+        let rules: Rules = include_str!("../tasks/day2_rpsls_rules.txt")
+            .parse()
+            .unwrap();
+        assert_eq!(rules.shape_count(), 5);
+
+        let choice = |symbol| rules.opponent_choice(symbol).unwrap();
+        let (rock, paper, scissors, lizard, spock) = (
+            choice("A"),
+            choice("B"),
+            choice("C"),
+            choice("D"),
+            choice("E"),
+        );
+
+        for (winner, loser) in [
+            (scissors, paper),
+            (paper, rock),
+            (rock, lizard),
+            (lizard, spock),
+            (spock, scissors),
+            (scissors, lizard),
+            (lizard, paper),
+            (paper, spock),
+            (spock, rock),
+            (rock, scissors),
+        ] {
+            assert_eq!(rules.outcome(&winner, &loser), Outcome::Win);
+            assert_eq!(rules.outcome(&loser, &winner), Outcome::Loss);
+        }
+        assert_eq!(rules.outcome(&spock, &spock), Outcome::Draw);
+    }
+
+    #[test]
+    fn test_guide_analysis() {
+        let rules = Rules::default();
+        let guide = parse_guide(TEST_FILE.as_bytes(), &rules).unwrap();
+
+        assert_eq!(
+            guide_breakdown(&guide, &rules).unwrap(),
+            Breakdown {
+                wins: 1,
+                draws: 1,
+                losses: 1,
+                score: 15
+            }
+        );
+        assert_eq!(
+            optimal_breakdown(guide.iter().map(|(opponent, _)| opponent), &rules),
+            Breakdown {
+                wins: 3,
+                draws: 0,
+                losses: 0,
+                score: 24
+            }
+        );
+
+        let mappings = mapping_breakdowns(&guide, &rules);
+        assert_eq!(mappings.len(), 6);
+
+        let (mapping, breakdown) = &mappings[0];
+        assert_eq!(breakdown.score, 24);
+        assert_eq!(
+            mapping,
+            &vec![
+                ("X".to_owned(), Choice(2)),
+                ("Y".to_owned(), Choice(1)),
+                ("Z".to_owned(), Choice(0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        assert!("shapes Rock=1 Paper=2".parse::<Rules>().is_err());
+        assert!("shapes Rock=1 Paper=2 Scissors=3".parse::<Rules>().is_err());
+    }
 }
@@ -1,92 +1,434 @@
-use std::{collections::HashSet, path::PathBuf};
+use std::{
+    fmt::{Display, Formatter},
+    fs::File,
+    io::{BufRead, BufReader},
+    ops::{BitAnd, BitOr},
+    path::PathBuf,
+};
 
 use anyhow::Result;
 use itertools::Itertools;
 
-pub(crate) fn solve(path: PathBuf) -> Result<()> {
-    let file = std::fs::read_to_string(path)?;
+pub fn solve(path: PathBuf) -> Result<()> {
+    let reader = BufReader::new(File::open(path)?);
 
-    let total_sum: i32 = file
-        .lines()
-        .map(|line| {
-            log::trace!("Raw line: {line}");
+    let total_sum = priority_sum(reader)?;
+    log::info!("Total sum: {total_sum}");
 
-            let first_half = &line[0..line.len() / 2];
-            let second_half = &line[line.len() / 2..line.len()];
+    Ok(())
+}
 
-            log::trace!("First half: {first_half}, second half: {second_half}");
+pub fn solve_b(path: PathBuf, group_size: usize) -> Result<()> {
+    let reader = BufReader::new(File::open(path)?);
 
-            let items = first_half
-                .chars()
-                .filter(|item| second_half.chars().any(|second_item| item == &second_item));
+    let total_sum = badge_priority_sum(reader, group_size)?;
+    log::info!("Total sum: {total_sum}");
 
-            let item_set: HashSet<char> = items.collect();
-            log::trace!("Item set: {item_set:?}");
+    Ok(())
+}
 
-            let rated_items: Vec<i32> = item_set
-                .into_iter()
-                .map(|char| {
-                    let value = (char as i32) - 96;
+pub fn solve_validate(path: PathBuf, group_size: usize) -> Result<()> {
+    let reader = BufReader::new(File::open(path)?);
 
-                    if value > 0 {
-                        value
-                    } else {
-                        value + 58
-                    }
-                })
-                .collect();
-            log::debug!("Rated items: {rated_items:?}");
+    let violations = validate(reader, group_size)?;
+    for violation in &violations {
+        log::warn!("{violation}");
+    }
+    log::info!("Found {} violations", violations.len());
 
-            rated_items[0]
+    Ok(())
+}
+
+/// Set of the item types within a rucksack, item `a` being stored in the lowest bit and
+/// `Z` in the 52nd bit, so that the priority of an item is its bit index plus one.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Rucksack(u64);
+
+impl Rucksack {
+    /// Every item type, intersecting with it leaves a set unchanged.
+    pub const ALL: Self = Self((1 << 52) - 1);
+
+    pub fn from_items(items: &str) -> Result<Self> {
+        items.chars().try_fold(Self::default(), |rucksack, item| {
+            Ok(rucksack | Self(1 << (priority(item)? - 1)))
         })
-        .sum();
+    }
 
-    log::info!("Total sum: {total_sum}");
+    /// Splits the items into the two equally sized compartments of a rucksack.
+    pub fn compartments(items: &str) -> Result<(Self, Self)> {
+        // Item types are ASCII letters, checking them first makes byte and item counts agree
+        if let Some(item) = items.chars().find(|&item| priority(item).is_err()) {
+            anyhow::bail!("Invalid item type {item:?}");
+        }
 
-    Ok(())
+        if !items.len().is_multiple_of(2) {
+            anyhow::bail!("Rucksack {items} can not be split into two equal compartments");
+        }
+
+        let (first_half, second_half) = items.split_at(items.len() / 2);
+        log::trace!("First half: {first_half}, second half: {second_half}");
+
+        Ok((
+            Self::from_items(first_half)?,
+            Self::from_items(second_half)?,
+        ))
+    }
+
+    pub fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(&self, item: char) -> bool {
+        priority(item).is_ok_and(|priority| self.0 & (1 << (priority - 1)) != 0)
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = char> + '_ {
+        ('a'..='z')
+            .chain('A'..='Z')
+            .filter(|&item| self.contains(item))
+    }
+
+    /// Returns the only item type within the set, failing if there are none or several.
+    pub fn single_item(&self) -> Result<char> {
+        match self.len() {
+            1 => Ok(self.items().next().expect("Set contains one item")),
+            0 => anyhow::bail!("No common item found"),
+            _ => anyhow::bail!("Multiple common items found: {}", self.items().join(", ")),
+        }
+    }
 }
 
-pub(crate) fn solve_b(path: PathBuf) -> Result<()> {
-    let file = std::fs::read_to_string(path)?;
+impl BitAnd for Rucksack {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        self.intersection(rhs)
+    }
+}
+
+impl BitOr for Rucksack {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        self.union(rhs)
+    }
+}
 
-    let total_sum: i32 = file
+/// Sums up the priorities of the items found in both compartments of each rucksack.
+pub fn priority_sum<R: BufRead>(reader: R) -> Result<u32> {
+    reader
         .lines()
-        .chunks(3)
-        .into_iter()
-        .map(|mut bags| {
-            if let Some((bag_1, bag_2, bag_3)) = bags.next_tuple() {
-                log::trace!("Raw bags: {bag_1} | {bag_2} | {bag_3}");
-
-                let items = bag_1
-                    .chars()
-                    .filter(|item| bag_2.chars().any(|second_item| item == &second_item))
-                    .filter(|item| bag_3.chars().any(|second_item| item == &second_item));
-
-                let item_set: HashSet<char> = items.collect();
-                log::trace!("Item set: {item_set:?}");
-
-                let rated_items: Vec<i32> = item_set
-                    .into_iter()
-                    .map(|char| {
-                        let value = (char as i32) - 96;
-
-                        if value > 0 {
-                            value
-                        } else {
-                            value + 58
-                        }
-                    })
-                    .collect();
-                log::debug!("Rated items: {rated_items:?}");
-
-                rated_items[0]
-            } else {
-                0
-            }
+        .enumerate()
+        .try_fold(0, |total_sum, (index, line)| {
+            let line = line?;
+            log::trace!("Raw line: {line}");
+
+            let item = Rucksack::compartments(&line)
+                .and_then(|(first, second)| (first & second).single_item())
+                .map_err(|err| anyhow::anyhow!("Line {}: {err}", index + 1))?;
+            log::debug!("Common item: {item}");
+
+            Ok(total_sum + priority(item)?)
         })
-        .sum();
+}
 
-    log::info!("Total sum: {total_sum}");
+/// Sums up the priorities of the badges shared by each group of `group_size` rucksacks.
+pub fn badge_priority_sum<R: BufRead>(reader: R, group_size: usize) -> Result<u32> {
+    if group_size == 0 {
+        anyhow::bail!("Group size has to be at least 1");
+    }
 
-    Ok(())
+    let mut total_sum = 0;
+
+    for (index, bags) in reader.lines().chunks(group_size).into_iter().enumerate() {
+        let bags: Vec<String> = bags.collect::<Result<_, _>>()?;
+        log::trace!("Raw bags: {}", bags.join(" | "));
+
+        if bags.len() != group_size {
+            anyhow::bail!(
+                "Group {} consists of {} instead of {group_size} rucksacks",
+                index + 1,
+                bags.len()
+            );
+        }
+
+        let badge = bags
+            .iter()
+            .try_fold(Rucksack::ALL, |common, bag| {
+                Ok::<_, anyhow::Error>(common & Rucksack::from_items(bag)?)
+            })
+            .and_then(|common| common.single_item())
+            .map_err(|err| anyhow::anyhow!("Group {}: {err}", index + 1))?;
+        log::debug!("Badge: {badge}");
+
+        total_sum += priority(badge)?;
+    }
+
+    Ok(total_sum)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    OddLength {
+        line: usize,
+        length: usize,
+    },
+    InvalidItems {
+        line: usize,
+        items: String,
+    },
+    DuplicatedItems {
+        line: usize,
+        items: String,
+    },
+    Badges {
+        first_line: usize,
+        last_line: usize,
+        badges: String,
+    },
+    IncompleteGroup {
+        first_line: usize,
+        size: usize,
+    },
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::OddLength { line, length } => {
+                write!(
+                    f,
+                    "Line {line}: Rucksack has an odd amount of {length} items"
+                )
+            }
+            Violation::InvalidItems { line, items } => {
+                write!(f, "Line {line}: Invalid item types {items:?}")
+            }
+            Violation::DuplicatedItems { line, items } => write!(
+                f,
+                "Line {line}: Expected exactly one item type in both compartments, found {items:?}"
+            ),
+            Violation::Badges {
+                first_line,
+                last_line,
+                badges,
+            } => write!(
+                f,
+                "Lines {first_line}-{last_line}: Expected exactly one badge, found {badges:?}"
+            ),
+            Violation::IncompleteGroup { first_line, size } => write!(
+                f,
+                "Line {first_line}: Group consists of only {size} rucksacks"
+            ),
+        }
+    }
+}
+
+/// Checks every rucksack and every group of `group_size` rucksacks, collecting all
+/// violations instead of stopping at the first one. Line numbers start at 1.
+pub fn validate<R: BufRead>(reader: R, group_size: usize) -> Result<Vec<Violation>> {
+    if group_size == 0 {
+        anyhow::bail!("Group size has to be at least 1");
+    }
+
+    let mut violations = vec![];
+    let mut group = Rucksack::ALL;
+    let mut group_len = 0;
+
+    let mut line_count = 0;
+
+    for line in reader.lines() {
+        let line = line?;
+        line_count += 1;
+        let line_number = line_count;
+
+        let (valid_items, invalid_items): (String, String) =
+            line.chars().partition(|&item| priority(item).is_ok());
+        if !invalid_items.is_empty() {
+            violations.push(Violation::InvalidItems {
+                line: line_number,
+                items: invalid_items.chars().unique().collect(),
+            });
+        }
+
+        // Items are counted as characters, so that invalid multi-byte items are not split
+        let items: Vec<char> = line.chars().collect();
+        if !items.len().is_multiple_of(2) {
+            violations.push(Violation::OddLength {
+                line: line_number,
+                length: items.len(),
+            });
+        } else {
+            let (first_half, second_half) = items.split_at(items.len() / 2);
+            let valid = |half: &[char]| -> String {
+                half.iter()
+                    .copied()
+                    .filter(|&item| priority(item).is_ok())
+                    .collect()
+            };
+
+            let duplicated = Rucksack::from_items(&valid(first_half))?
+                & Rucksack::from_items(&valid(second_half))?;
+            if duplicated.len() != 1 {
+                violations.push(Violation::DuplicatedItems {
+                    line: line_number,
+                    items: duplicated.items().collect(),
+                });
+            }
+        }
+
+        group = group & Rucksack::from_items(&valid_items)?;
+        group_len += 1;
+
+        if group_len == group_size {
+            if group.len() != 1 {
+                violations.push(Violation::Badges {
+                    first_line: line_number + 1 - group_size,
+                    last_line: line_number,
+                    badges: group.items().collect(),
+                });
+            }
+
+            group = Rucksack::ALL;
+            group_len = 0;
+        }
+    }
+
+    if group_len > 0 {
+        violations.push(Violation::IncompleteGroup {
+            first_line: line_count + 1 - group_len,
+            size: group_len,
+        });
+    }
+
+    Ok(violations)
+}
+
+/// Returns the priority of an item type, `a` through `z` ranging from 1 to 26 and `A`
+/// through `Z` from 27 to 52.
+pub fn priority(item: char) -> Result<u32> {
+    match item {
+        'a'..='z' => Ok(item as u32 - 'a' as u32 + 1),
+        'A'..='Z' => Ok(item as u32 - 'A' as u32 + 27),
+        _ => anyhow::bail!("Invalid item type {item:?}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_FILE: &str = include_str!("../tasks/day3_dev.txt");
+
+    #[test]
+    fn test_priority_sums() {
+        assert_eq!(priority_sum(TEST_FILE.as_bytes()).unwrap(), 157);
+        assert_eq!(badge_priority_sum(TEST_FILE.as_bytes(), 3).unwrap(), 70);
+    }
+
+    #[test]
+    fn test_rucksack_set_operations() {
+        let first = Rucksack::from_items("abcZ").unwrap();
+        let second = Rucksack::from_items("cdZ").unwrap();
+
+        assert_eq!((first & second).items().collect::<String>(), "cZ");
+        assert_eq!((first | second).len(), 5);
+        assert!(Rucksack::from_items("ab1").is_err());
+        assert_eq!(priority('p').unwrap(), 16);
+        assert_eq!(priority('L').unwrap(), 38);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(validate(TEST_FILE.as_bytes(), 3).unwrap(), vec![]);
+
+        let violations = validate("abab\nxyz\nab1b\ncc\ndd\n".as_bytes(), 2).unwrap();
+        assert_eq!(
+            violations,
+            vec![
+                Violation::DuplicatedItems {
+                    line: 1,
+                    items: "ab".to_owned()
+                },
+                Violation::OddLength { line: 2, length: 3 },
+                Violation::Badges {
+                    first_line: 1,
+                    last_line: 2,
+                    badges: "".to_owned()
+                },
+                Violation::InvalidItems {
+                    line: 3,
+                    items: "1".to_owned()
+                },
+                Violation::Badges {
+                    first_line: 3,
+                    last_line: 4,
+                    badges: "".to_owned()
+                },
+                Violation::IncompleteGroup {
+                    first_line: 5,
+                    size: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_non_ascii() {
+        assert_eq!(
+            validate("aéb\naéba\n".as_bytes(), 2).unwrap(),
+            vec![
+                Violation::InvalidItems {
+                    line: 1,
+                    items: "é".to_owned()
+                },
+                Violation::OddLength { line: 1, length: 3 },
+                Violation::InvalidItems {
+                    line: 2,
+                    items: "é".to_owned()
+                },
+                Violation::Badges {
+                    first_line: 1,
+                    last_line: 2,
+                    badges: "ab".to_owned()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_groups() {
+        assert!(Rucksack::compartments("abc").is_err());
+        assert!(Rucksack::from_items("ab").unwrap().single_item().is_err());
+        assert!(Rucksack::default().single_item().is_err());
+        assert!(badge_priority_sum("ab\ncd\n".as_bytes(), 2).is_err());
+        assert!(badge_priority_sum(TEST_FILE.as_bytes(), 4).is_err());
+        assert_eq!(badge_priority_sum("ab\nbc\n".as_bytes(), 2).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_priority_sum_errors() {
+        assert!(priority_sum("abcd\n".as_bytes()).is_err());
+        assert!(priority_sum("abab\n".as_bytes()).is_err());
+        assert!(priority_sum("aa\n\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_non_ascii_items() {
+        assert!(Rucksack::compartments("aéb").is_err());
+        assert!(Rucksack::compartments("aéba").is_err());
+        assert!(priority_sum("aéb\n".as_bytes()).is_err());
+    }
 }
@@ -1,58 +1,246 @@
-use std::{num::ParseIntError, path::PathBuf};
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::PathBuf,
+};
 
 use anyhow::Result;
 
-pub(crate) fn solve(path: PathBuf) -> Result<()> {
-    let file = std::fs::read_to_string(path)?;
+use crate::interval::{Interval, IntervalSet};
 
-    let results_iter = file.lines().map(|pairs| {
-        let mut pairs = pairs.split(',');
+pub fn solve(path: PathBuf, print_matrices: bool) -> Result<()> {
+    let reader = BufReader::new(File::open(path)?);
 
-        let elf_1 = pairs.next().map(|entry| {
-            let mut entries = entry.split('-');
+    let mut summary = Summary::default();
+    for (index, matrix) in conflict_matrices(reader).enumerate() {
+        let matrix = matrix?;
 
-            (entries.next(), entries.next())
-        });
-        let elf_2 = pairs.next().map(|entry| {
-            let mut entries = entry.split('-');
+        if print_matrices {
+            println!("Line {}:", index + 1);
+            print!("{matrix}");
+        }
+
+        summary.record(&matrix);
+    }
+
+    log::info!("Total pair intersections: {}", summary.contained_pairs);
+    log::info!("Total pair overlaps: {}", summary.overlapping_pairs);
+    log::info!(
+        "Lines with intersections: {} | with overlaps: {}",
+        summary.lines_with_containment,
+        summary.lines_with_overlap
+    );
+
+    log::info!("Total sections covered: {}", summary.coverage.coverage());
+    let gaps = summary.coverage.gaps();
+    if gaps.is_empty() {
+        log::info!("Uncovered sections: none");
+    } else {
+        log::info!("Uncovered sections: {gaps}");
+    }
 
-            (entries.next(), entries.next())
-        });
+    Ok(())
+}
 
-        log::trace!("elf_1: {elf_1:?} | elf_2: {elf_2:?}");
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Summary {
+    /// Pairs of elves in which one assignment fully contains the other
+    pub contained_pairs: usize,
+    /// Pairs of elves whose assignments overlap at all
+    pub overlapping_pairs: usize,
+    /// Lines containing at least one fully contained assignment
+    pub lines_with_containment: usize,
+    /// Lines containing at least one overlapping pair of assignments
+    pub lines_with_overlap: usize,
+    /// Sections assigned to at least one elf
+    pub coverage: IntervalSet,
+}
 
-        if let Some((Some(elf_1_min), Some(elf_1_max))) = elf_1 {
-            if let Some((Some(elf_2_min), Some(elf_2_max))) = elf_2 {
-                let elf_1_min: Result<u8, ParseIntError> = elf_1_min.parse();
-                let elf_1_max: Result<u8, ParseIntError> = elf_1_max.parse();
-                let elf_2_min: Result<u8, ParseIntError> = elf_2_min.parse();
-                let elf_2_max: Result<u8, ParseIntError> = elf_2_max.parse();
+impl Summary {
+    pub fn record(&mut self, matrix: &ConflictMatrix) {
+        let contained_pairs = matrix.contained_pairs();
+        let overlapping_pairs = matrix.overlapping_pairs();
+
+        self.contained_pairs += contained_pairs;
+        self.overlapping_pairs += overlapping_pairs;
+        self.lines_with_containment += usize::from(contained_pairs > 0);
+        self.lines_with_overlap += usize::from(overlapping_pairs > 0);
+        self.coverage.extend(matrix.assignments.iter().copied());
+    }
+}
 
-                if let (Ok(elf_1_min), Ok(elf_1_max), Ok(elf_2_min), Ok(elf_2_max)) =
-                    (elf_1_min, elf_1_max, elf_2_min, elf_2_max)
-                {
-                    log::debug!(
-                        "elf_1: {elf_1_min}, {elf_1_max} | elf_2: {elf_2_min}, {elf_2_max}"
-                    );
+/// Relation of one elf's assignment to another elf's assignment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conflict {
+    None,
+    Overlaps,
+    Contains,
+    ContainedBy,
+    Equal,
+}
 
-                    let includes = elf_1_min <= elf_2_min && elf_1_max >= elf_2_max
-                        || elf_2_min <= elf_1_min && elf_2_max >= elf_1_max;
+impl Conflict {
+    pub fn between(assignment: &Interval, other: &Interval) -> Self {
+        match (assignment.contains(other), other.contains(assignment)) {
+            (true, true) => Self::Equal,
+            (true, false) => Self::Contains,
+            (false, true) => Self::ContainedBy,
+            (false, false) if assignment.overlaps(other) => Self::Overlaps,
+            (false, false) => Self::None,
+        }
+    }
+
+    fn symbol(&self) -> char {
+        match self {
+            Conflict::None => '.',
+            Conflict::Overlaps => 'o',
+            Conflict::Contains => '>',
+            Conflict::ContainedBy => '<',
+            Conflict::Equal => '=',
+        }
+    }
+}
 
-                    let overlaps = !(elf_1_max < elf_2_min || elf_2_max < elf_1_min);
+/// Conflicts between every pair of assignments on a single line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictMatrix {
+    assignments: Vec<Interval>,
+}
 
-                    return (includes, overlaps);
-                }
+impl ConflictMatrix {
+    pub fn new(assignments: Vec<Interval>) -> Self {
+        Self { assignments }
+    }
+
+    pub fn assignments(&self) -> &[Interval] {
+        &self.assignments
+    }
+
+    /// Relation of the assignment of elf `row` to the one of elf `column`.
+    pub fn get(&self, row: usize, column: usize) -> Conflict {
+        Conflict::between(&self.assignments[row], &self.assignments[column])
+    }
+
+    /// Every unordered pair of elves along with their conflict.
+    pub fn pairs(&self) -> impl Iterator<Item = (usize, usize, Conflict)> + '_ {
+        (0..self.assignments.len()).flat_map(move |row| {
+            (row + 1..self.assignments.len())
+                .map(move |column| (row, column, self.get(row, column)))
+        })
+    }
+
+    pub fn contained_pairs(&self) -> usize {
+        self.pairs()
+            .filter(|(_, _, conflict)| {
+                matches!(
+                    conflict,
+                    Conflict::Contains | Conflict::ContainedBy | Conflict::Equal
+                )
+            })
+            .count()
+    }
+
+    pub fn overlapping_pairs(&self) -> usize {
+        self.pairs()
+            .filter(|(_, _, conflict)| *conflict != Conflict::None)
+            .count()
+    }
+}
+
+impl Display for ConflictMatrix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (row, assignment) in self.assignments.iter().enumerate() {
+            for column in 0..self.assignments.len() {
+                let symbol = if row == column {
+                    '-'
+                } else {
+                    self.get(row, column).symbol()
+                };
+
+                write!(f, "{symbol}")?;
             }
+
+            writeln!(f, " {assignment}")?;
         }
 
-        (false, false)
-    });
+        Ok(())
+    }
+}
+
+/// Summarizes the assignments, reading them line by line.
+pub fn summarize<R: BufRead>(reader: R) -> Result<Summary> {
+    conflict_matrices(reader).try_fold(Summary::default(), |mut summary, matrix| {
+        summary.record(&matrix?);
 
-    let total_pair_intersections = results_iter.clone().filter(|result| result.0).count();
-    log::info!("Total pair intersections: {total_pair_intersections}");
+        Ok(summary)
+    })
+}
 
-    let total_pair_overlaps = results_iter.filter(|result| result.1).count();
-    log::info!("Total pair overlaps: {total_pair_overlaps}");
+/// Streams the conflict matrix of every line of assignments.
+pub fn conflict_matrices<R: BufRead>(reader: R) -> impl Iterator<Item = Result<ConflictMatrix>> {
+    reader.lines().enumerate().map(|(index, line)| {
+        let assignments = parse_assignments(&line?)
+            .map_err(|err| anyhow::anyhow!("Line {}: {err}", index + 1))?;
+        log::debug!("assignments: {assignments:?}");
 
-    Ok(())
+        Ok(ConflictMatrix::new(assignments))
+    })
+}
+
+fn parse_assignments(line: &str) -> Result<Vec<Interval>> {
+    log::trace!("line: {line:?}");
+
+    line.split(',').map(str::parse).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_FILE: &str = include_str!("../tasks/day4_dev.txt");
+
+    #[test]
+    fn test_summarize() {
+        let summary = summarize(TEST_FILE.as_bytes()).unwrap();
+
+        assert_eq!(summary.contained_pairs, 2);
+        assert_eq!(summary.overlapping_pairs, 4);
+        assert_eq!(summary.coverage.to_string(), "2-9");
+        assert!(summary.coverage.gaps().is_empty());
+    }
+
+    #[test]
+    fn test_large_sections() {
+        let summary = summarize("2-300,400-1000\n".as_bytes()).unwrap();
+
+        assert_eq!(summary.overlapping_pairs, 0);
+        assert_eq!(summary.coverage.coverage(), 900);
+        assert_eq!(summary.coverage.gaps().to_string(), "301-399");
+        assert!(summarize("2-4,\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_conflict_matrix() {
+        let matrix = conflict_matrices("2-8,3-7,7-9,10-12,3-7\n".as_bytes())
+            .next()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(matrix.get(0, 1), Conflict::Contains);
+        assert_eq!(matrix.get(1, 0), Conflict::ContainedBy);
+        assert_eq!(matrix.get(1, 2), Conflict::Overlaps);
+        assert_eq!(matrix.get(2, 3), Conflict::None);
+        assert_eq!(matrix.get(1, 4), Conflict::Equal);
+        assert_eq!(matrix.contained_pairs(), 3);
+        assert_eq!(matrix.overlapping_pairs(), 6);
+        assert_eq!(
+            matrix.to_string(),
+            "->o.> 2-8\n<-o.= 3-7\noo-.o 7-9\n...-. 10-12\n<=o.- 3-7\n"
+        );
+
+        let summary = summarize("2-8,3-7,7-9,10-12,3-7\n1-1\n".as_bytes()).unwrap();
+        assert_eq!(summary.lines_with_containment, 1);
+        assert_eq!(summary.lines_with_overlap, 1);
+    }
 }
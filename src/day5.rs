@@ -1,104 +1,456 @@
-use std::path::PathBuf;
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
 
 use anyhow::Result;
 use chumsky::prelude::*;
+use itertools::Itertools;
+
+pub fn solve(
+    path: PathBuf,
+    crane: CraneModel,
+    capacity: Option<usize>,
+    on_illegal_move: IllegalMovePolicy,
+    step: Option<usize>,
+    trace: Option<PathBuf>,
+    export: Option<PathBuf>,
+) -> Result<()> {
+    let crane = crane.build(capacity)?;
 
-pub(crate) fn solve(path: PathBuf, part_a: bool) -> Result<()> {
     let file = std::fs::read_to_string(path)?;
 
-    let (stack_elements, moves) = file_parser().parse(file).map_err(|err| {
-        anyhow::Error::msg(format!("An error occurred while parsing the file: {err:?}"))
-    })?;
-    log::trace!("stack_elements: {stack_elements:?}");
+    let (yard, moves) = parse_file(&file)?;
+    log::trace!("yard: {yard:?}");
     log::trace!("moves: {moves:?}");
 
-    let stack_amount =
-        stack_elements.iter().fold(
-            0,
-            |acc, item| {
-                if acc > item.len() {
-                    acc
-                } else {
-                    item.len()
-                }
-            },
-        );
+    if step.is_some_and(|step| step > moves.len()) {
+        anyhow::bail!("There are only {} moves", moves.len());
+    }
 
-    let mut stacks: Vec<Vec<char>> = vec![vec![]; stack_amount];
+    // The moves are applied to a single copy, only the state at the requested step is kept
+    let mut final_state = yard.clone();
+    let mut step_state = None;
+    for (index, next_move) in moves.iter().enumerate() {
+        if step == Some(index) {
+            step_state = Some(final_state.clone());
+        }
 
-    for elements in stack_elements {
-        for (index, element) in elements.iter().enumerate() {
-            if let Some(element) = element {
-                if let Some(stack) = stacks.get_mut(index) {
-                    stack.insert(0, *element);
-                }
+        if let Err(illegal_move) = final_state.apply(next_move, crane.as_ref()) {
+            if on_illegal_move == IllegalMovePolicy::Stop {
+                anyhow::bail!("Stopping at illegal move: {illegal_move}");
             }
+
+            log::warn!("Skipping illegal move: {illegal_move}");
         }
     }
+    let step_state = step_state.as_ref().unwrap_or(&final_state);
+
+    if let Some(trace) = trace {
+        let mut writer = BufWriter::new(File::create(trace)?);
+        yard.write_trace(&mut writer, &moves, crane.as_ref())?;
+        writer.flush()?;
+    }
+
+    if let Some(step) = step {
+        log::info!("Stacks after move {step}:");
+        print!("{step_state}");
+    }
+
+    if let Some(export) = export {
+        let mut writer = BufWriter::new(File::create(export)?);
+        step_state.write_puzzle(&mut writer, &moves[step.unwrap_or(moves.len())..])?;
+        writer.flush()?;
+    }
+
+    log::debug!("Stacks: {:?}", final_state.stacks);
+
+    let answer = final_state.top_crates();
+    log::info!("Answer: {answer}");
 
-    if part_a {
-        for (count, source, target) in moves {
-            for _ in 0..count {
-                if let Some(element) = stacks[(source - 1) as usize].pop() {
-                    stacks[(target - 1) as usize].push(element);
+    Ok(())
+}
+
+/// Stacks of crates, each stack listing its crates from the bottom to the top.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateYard {
+    stacks: Vec<Vec<char>>,
+}
+
+impl CrateYard {
+    /// Builds the stacks from the rows of the drawing, listed from the top to the bottom.
+    pub fn from_rows(rows: &[Vec<Option<char>>]) -> Self {
+        let stack_amount = rows.iter().map(Vec::len).max().unwrap_or_default();
+
+        let mut stacks: Vec<Vec<char>> = vec![vec![]; stack_amount];
+
+        for elements in rows.iter().rev() {
+            for (index, element) in elements.iter().enumerate() {
+                if let Some(element) = element {
+                    stacks[index].push(*element);
                 }
             }
         }
-    } else {
-        for (count, source, target) in moves {
-            let mut temp_elements = vec![];
 
-            for _ in 0..count {
-                if let Some(element) = stacks[(source - 1) as usize].pop() {
-                    temp_elements.insert(0, element);
-                }
+        Self { stacks }
+    }
+
+    pub fn stacks(&self) -> &[Vec<char>] {
+        &self.stacks
+    }
+
+    /// Checks whether the move can be carried out on the current stacks.
+    pub fn check(&self, step: &Move) -> Result<(), IllegalMove> {
+        for stack in [step.source, step.target] {
+            if stack == 0 || stack > self.stacks.len() {
+                return Err(IllegalMove::InvalidStack {
+                    line: step.line,
+                    stack,
+                    stack_count: self.stacks.len(),
+                });
             }
+        }
 
-            stacks[(target - 1) as usize].extend(temp_elements);
+        let available = self.stacks[step.source - 1].len();
+        if available < step.count {
+            return Err(IllegalMove::InsufficientCrates {
+                line: step.line,
+                stack: step.source,
+                available,
+                requested: step.count,
+            });
         }
+
+        Ok(())
     }
 
-    log::debug!("Stacks: {stacks:?}");
+    /// Carries out the move if it is legal, leaving the stacks untouched otherwise.
+    pub fn apply(&mut self, step: &Move, crane: &dyn Crane) -> Result<(), IllegalMove> {
+        self.check(step)?;
 
-    let mut answer = String::new();
-    for stack in stacks {
-        if let Some(element) = stack.last() {
-            answer.push(*element);
+        let (source, target) = (step.source - 1, step.target - 1);
+        if source == target {
+            return Ok(());
         }
+
+        let mut source_stack = std::mem::take(&mut self.stacks[source]);
+        crane.move_crates(&mut source_stack, &mut self.stacks[target], step.count);
+        self.stacks[source] = source_stack;
+
+        Ok(())
     }
 
-    log::info!("Answer: {answer}");
+    /// Yields the state of the yard after each of the moves, illegal moves being skipped.
+    pub fn states<'a>(
+        &self,
+        moves: &'a [Move],
+        crane: &'a dyn Crane,
+    ) -> impl Iterator<Item = CrateYard> + 'a {
+        moves.iter().scan(self.clone(), move |yard, step| {
+            if let Err(err) = yard.apply(step, crane) {
+                log::debug!("Skipped illegal move: {err}");
+            }
 
-    Ok(())
+            Some(yard.clone())
+        })
+    }
+
+    /// State after the first `step` moves, `0` being the initial state.
+    pub fn state_after(&self, moves: &[Move], crane: &dyn Crane, step: usize) -> Option<CrateYard> {
+        match step {
+            0 => Some(self.clone()),
+            step => self.states(moves, crane).nth(step - 1),
+        }
+    }
+
+    /// Writes the initial drawing followed by each move and the drawing after it.
+    pub fn write_trace<W: Write>(
+        &self,
+        writer: &mut W,
+        moves: &[Move],
+        crane: &dyn Crane,
+    ) -> Result<()> {
+        writeln!(writer, "{self}")?;
+
+        for (step, state) in moves.iter().zip(self.states(moves, crane)) {
+            writeln!(writer, "{step}")?;
+            writeln!(writer, "{state}")?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the drawing followed by the moves in the puzzle's format, which can be read
+    /// back using [`parse_file`].
+    pub fn write_puzzle<W: Write>(&self, writer: &mut W, moves: &[Move]) -> Result<()> {
+        writeln!(writer, "{self}")?;
+
+        for step in moves {
+            writeln!(writer, "{step}")?;
+        }
+
+        Ok(())
+    }
+
+    pub fn top_crates(&self) -> String {
+        self.stacks
+            .iter()
+            .filter_map(|stack| stack.last())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    pub count: usize,
+    /// Number of the stack to take the crates from, starting at 1
+    pub source: usize,
+    /// Number of the stack to put the crates on, starting at 1
+    pub target: usize,
+    /// Line of the move within the input, starting at 1
+    pub line: usize,
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "move {} from {} to {}",
+            self.count, self.source, self.target
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IllegalMove {
+    InvalidStack {
+        line: usize,
+        stack: usize,
+        stack_count: usize,
+    },
+    InsufficientCrates {
+        line: usize,
+        stack: usize,
+        available: usize,
+        requested: usize,
+    },
+}
+
+impl Display for IllegalMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IllegalMove::InvalidStack {
+                line,
+                stack,
+                stack_count,
+            } => write!(
+                f,
+                "Line {line}: Stack {stack} does not exist, there are {stack_count} stacks"
+            ),
+            IllegalMove::InsufficientCrates {
+                line,
+                stack,
+                available,
+                requested,
+            } => write!(
+                f,
+                "Line {line}: Can not move {requested} crates from stack {stack} holding {available}"
+            ),
+        }
+    }
+}
+
+/// How to proceed once an illegal move is encountered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum IllegalMovePolicy {
+    /// Abort at the first illegal move
+    Stop,
+    /// Report and skip every illegal move
+    Continue,
+}
+
+/// Crane moving crates from the top of one stack to the top of another.
+pub trait Crane {
+    /// Moves the topmost `count` crates, `count` never exceeding the crates on `source`.
+    fn move_crates(&self, source: &mut Vec<char>, target: &mut Vec<char>, count: usize);
+}
+
+/// Moves one crate at a time, reversing the order of the moved crates.
+pub struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn move_crates(&self, source: &mut Vec<char>, target: &mut Vec<char>, count: usize) {
+        target.extend(source.drain(source.len() - count..).rev());
+    }
+}
+
+/// Moves all crates at once, retaining their order.
+pub struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn move_crates(&self, source: &mut Vec<char>, target: &mut Vec<char>, count: usize) {
+        target.extend(source.split_off(source.len() - count));
+    }
+}
+
+/// Moves at most `capacity` crates at once, retaining their order within each lift.
+pub struct LimitedCrane {
+    capacity: usize,
 }
 
-fn file_parser(
-) -> impl Parser<char, (Vec<Vec<Option<char>>>, Vec<(u8, u8, u8)>), Error = Simple<char>> {
+impl LimitedCrane {
+    pub fn new(capacity: usize) -> Result<Self> {
+        if capacity == 0 {
+            anyhow::bail!("Crane capacity has to be at least 1");
+        }
+
+        Ok(Self { capacity })
+    }
+}
+
+impl Crane for LimitedCrane {
+    fn move_crates(&self, source: &mut Vec<char>, target: &mut Vec<char>, count: usize) {
+        let mut remaining = count;
+
+        while remaining > 0 {
+            let lifted = remaining.min(self.capacity);
+            target.extend(source.split_off(source.len() - lifted));
+            remaining -= lifted;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CraneModel {
+    #[value(name = "9000")]
+    CrateMover9000,
+    #[value(name = "9001")]
+    CrateMover9001,
+    Limited,
+}
+
+impl CraneModel {
+    pub fn build(self, capacity: Option<usize>) -> Result<Box<dyn Crane>> {
+        Ok(match (self, capacity) {
+            (CraneModel::CrateMover9000, _) => Box::new(CrateMover9000),
+            (CraneModel::CrateMover9001, _) => Box::new(CrateMover9001),
+            (CraneModel::Limited, Some(capacity)) => Box::new(LimitedCrane::new(capacity)?),
+            (CraneModel::Limited, None) => anyhow::bail!("A limited crane requires a capacity"),
+        })
+    }
+}
+
+impl Display for CrateYard {
+    /// Draws the stacks in the puzzle's format, followed by the row of stack numbers.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let height = self.stacks.iter().map(Vec::len).max().unwrap_or_default();
+
+        for level in (0..height).rev() {
+            let row = self
+                .stacks
+                .iter()
+                .map(|stack| match stack.get(level) {
+                    Some(element) => format!("[{element}]"),
+                    None => "   ".to_owned(),
+                })
+                .join(" ");
+
+            writeln!(f, "{}", row.trim_end())?;
+        }
+
+        let numbers = (1..=self.stacks.len())
+            .map(|number| format!(" {number} "))
+            .join(" ");
+
+        writeln!(f, "{}", numbers.trim_end())
+    }
+}
+
+/// Parses the drawing and the moves, numbering the lines of the moves starting at 1.
+pub fn parse_file(file: &str) -> Result<(CrateYard, Vec<Move>)> {
+    let ((stack_elements, stack_amount), parsed_moves) =
+        file_parser().parse(file).map_err(|err| {
+            anyhow::Error::msg(format!("An error occurred while parsing the file: {err:?}"))
+        })?;
+
+    // The parser yields the character offset of each move, from which its line is counted
+    let chars: Vec<char> = file.chars().collect();
+    let (mut line, mut position) = (1, 0);
+    let moves = parsed_moves
+        .into_iter()
+        .map(|((count, source, target), offset)| {
+            let start = offset
+                + chars[offset..]
+                    .iter()
+                    .take_while(|char| char.is_whitespace())
+                    .count();
+
+            line += chars[position..start]
+                .iter()
+                .filter(|&&char| char == '\n')
+                .count();
+            position = start;
+
+            Move {
+                count,
+                source,
+                target,
+                line,
+            }
+        })
+        .collect();
+
+    // Trailing empty stacks only show up within the row of stack numbers
+    let mut yard = CrateYard::from_rows(&stack_elements);
+    let stack_amount = stack_amount.max(yard.stacks.len());
+    yard.stacks.resize(stack_amount, vec![]);
+
+    Ok((yard, moves))
+}
+
+type StackRows = Vec<Vec<Option<char>>>;
+/// Count, source and target of a move along with the character offset it starts at.
+type ParsedMove = ((usize, usize, usize), usize);
+
+fn file_parser() -> impl Parser<char, ((StackRows, usize), Vec<ParsedMove>), Error = Simple<char>> {
     block_section_parser()
-        // Ignore bucket numbers
-        .then_ignore(
+        // Only the amount of bucket numbers is of interest
+        .then(
             text::whitespace()
-                .then_ignore(text::int(10).padded())
-                .repeated(),
+                .ignore_then(text::int(10).padded())
+                .repeated()
+                .map(|numbers| numbers.len()),
         )
         .then(move_parser())
         .then_ignore(end())
 }
 
-fn move_parser() -> impl Parser<char, Vec<(u8, u8, u8)>, Error = Simple<char>> {
+fn move_parser() -> impl Parser<char, Vec<ParsedMove>, Error = Simple<char>> {
     (just("move")
         .padded()
-        .ignore_then(text::int(10).from_str::<u8>().unwrapped())
+        .ignore_then(number_parser())
         .then_ignore(just("from").padded())
-        .then(text::int(10).from_str::<u8>().unwrapped())
+        .then(number_parser())
         .then_ignore(just("to").padded())
-        .then(text::int(10).from_str::<u8>().unwrapped())
-        .map(|((a, b), c)| (a, b, c)))
+        .then(number_parser())
+        .map_with_span(|((count, source), target), span: std::ops::Range<usize>| {
+            ((count, source, target), span.start)
+        }))
     .then_ignore(text::newline())
     .repeated()
 }
 
-fn block_section_parser() -> impl Parser<char, Vec<Vec<Option<char>>>, Error = Simple<char>> {
+fn number_parser() -> impl Parser<char, usize, Error = Simple<char>> {
+    text::int(10).try_map(|number: String, span| {
+        number
+            .parse()
+            .map_err(|err| Simple::custom(span, format!("{err}")))
+    })
+}
+
+fn block_section_parser() -> impl Parser<char, StackRows, Error = Simple<char>> {
     block_parser()
         .repeated()
         .collect::<Vec<Option<char>>>()
@@ -112,17 +464,181 @@ fn block_parser() -> impl Parser<char, Option<char>, Error = Simple<char>> {
 
 fn filled_block_parser() -> impl Parser<char, Option<char>, Error = Simple<char>> {
     just('[')
-        .ignore_then(text::ident())
-        .map(|mut str| str.pop())
+        .ignore_then(filter(|c: &char| {
+            !c.is_whitespace() && *c != '[' && *c != ']'
+        }))
+        .map(Some)
         .then_ignore(just("] ").or(just("]")))
         .labelled("block")
 }
 
 fn empty_block_parser() -> impl Parser<char, Option<char>, Error = Simple<char>> {
-    filter(|c: &char| c.is_ascii_whitespace())
-        .repeated()
-        .exactly(4)
-        .ignored()
+    just("   ")
+        .then_ignore(just(' ').or_not())
         .map(|_| None)
         .labelled("block")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_FILE: &str = include_str!("../tasks/day5_dev.txt");
+
+    fn parse_dev_file() -> (CrateYard, Vec<Move>) {
+        parse_file(TEST_FILE).unwrap()
+    }
+
+    fn new_move(count: usize, source: usize, target: usize, line: usize) -> Move {
+        Move {
+            count,
+            source,
+            target,
+            line,
+        }
+    }
+
+    #[test]
+    fn test_file_parser() {
+        let (yard, moves) = parse_dev_file();
+
+        assert_eq!(
+            yard.stacks(),
+            &[vec!['Z', 'N'], vec!['M', 'C', 'D'], vec!['P']]
+        );
+        assert_eq!(
+            moves,
+            vec![
+                new_move(1, 2, 1, 6),
+                new_move(3, 1, 3, 7),
+                new_move(2, 2, 1, 8),
+                new_move(1, 1, 2, 9)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_states() {
+        let (yard, moves) = parse_dev_file();
+
+        assert_eq!(yard.states(&moves, &CrateMover9000).count(), 4);
+        assert_eq!(
+            yard.state_after(&moves, &CrateMover9000, 0),
+            Some(yard.clone())
+        );
+        assert_eq!(yard.state_after(&moves, &CrateMover9000, 5), None);
+        assert_eq!(
+            yard.state_after(&moves, &CrateMover9000, 1)
+                .unwrap()
+                .to_string(),
+            "[D]\n[N] [C]\n[Z] [M] [P]\n 1   2   3\n"
+        );
+    }
+
+    #[test]
+    fn test_cranes() {
+        let (yard, moves) = parse_dev_file();
+        let top_crates =
+            |crane: &dyn Crane| yard.states(&moves, crane).last().unwrap().top_crates();
+
+        assert_eq!(top_crates(&CrateMover9000), "CMZ");
+        assert_eq!(top_crates(&CrateMover9001), "MCD");
+        assert_eq!(top_crates(&LimitedCrane::new(1).unwrap()), "CMZ");
+        assert_eq!(top_crates(&LimitedCrane::new(3).unwrap()), "MCD");
+        assert!(LimitedCrane::new(0).is_err());
+
+        let (mut source, mut target) = (vec!['A', 'B', 'C', 'D', 'E'], vec![]);
+        LimitedCrane::new(2)
+            .unwrap()
+            .move_crates(&mut source, &mut target, 5);
+        assert_eq!(source, vec![]);
+        assert_eq!(target, vec!['D', 'E', 'B', 'C', 'A']);
+    }
+
+    #[test]
+    fn test_illegal_moves() {
+        let (mut yard, _) = parse_dev_file();
+        let moves = [
+            new_move(1, 0, 1, 6),
+            new_move(4, 1, 2, 7),
+            new_move(1, 3, 4, 8),
+            new_move(2, 1, 3, 9),
+        ];
+
+        assert_eq!(
+            moves
+                .iter()
+                .filter_map(|step| yard.apply(step, &CrateMover9000).err())
+                .collect::<Vec<_>>(),
+            vec![
+                IllegalMove::InvalidStack {
+                    line: 6,
+                    stack: 0,
+                    stack_count: 3
+                },
+                IllegalMove::InsufficientCrates {
+                    line: 7,
+                    stack: 1,
+                    available: 2,
+                    requested: 4
+                },
+                IllegalMove::InvalidStack {
+                    line: 8,
+                    stack: 4,
+                    stack_count: 3
+                },
+            ]
+        );
+        assert_eq!(yard.top_crates(), "DZ");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (yard, moves) = parse_dev_file();
+
+        let mut puzzle = vec![];
+        yard.write_puzzle(&mut puzzle, &moves).unwrap();
+        assert_eq!(String::from_utf8(puzzle).unwrap(), TEST_FILE);
+
+        for (step, state) in yard.states(&moves, &CrateMover9000).enumerate() {
+            let mut puzzle = vec![];
+            state.write_puzzle(&mut puzzle, &moves[step + 1..]).unwrap();
+
+            let (parsed_state, parsed_moves) =
+                parse_file(&String::from_utf8(puzzle).unwrap()).unwrap();
+            assert_eq!(parsed_state, state);
+            assert_eq!(parsed_moves.len(), moves.len() - step - 1);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_sparse_stacks() {
+        for stacks in [
+            vec![vec![], vec!['B', 'C'], vec![], vec!['1'], vec![]],
+            vec![vec![], vec![]],
+        ] {
+            let yard = CrateYard { stacks };
+
+            let mut puzzle = vec![];
+            yard.write_puzzle(&mut puzzle, &[]).unwrap();
+
+            let (parsed, moves) = parse_file(&String::from_utf8(puzzle).unwrap()).unwrap();
+            assert_eq!(parsed, yard);
+            assert!(moves.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_trace() {
+        let (yard, moves) = parse_dev_file();
+
+        let mut trace = vec![];
+        assert!(yard
+            .write_trace(&mut trace, &moves[..1], &CrateMover9000)
+            .is_ok());
+        assert_eq!(
+            String::from_utf8(trace).unwrap(),
+            "    [D]\n[N] [C]\n[Z] [M] [P]\n 1   2   3\n\nmove 1 from 2 to 1\n[D]\n[N] [C]\n[Z] [M] [P]\n 1   2   3\n\n"
+        );
+    }
+}
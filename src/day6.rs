@@ -1,28 +1,375 @@
-use std::{collections::HashSet, path::PathBuf};
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    fs::File,
+    io::{BufRead, BufReader, Bytes, Read},
+    net::TcpListener,
+    path::PathBuf,
+};
 
 use anyhow::Result;
 
-pub(crate) fn solve(path: PathBuf, window_size: usize) -> Result<()> {
-    let file = std::fs::read_to_string(path)?;
+pub fn solve(path: PathBuf, window_size: usize, all: bool) -> Result<()> {
+    let reader = BufReader::new(File::open(path)?);
 
-    let chars: Vec<char> = file.as_str().chars().collect();
+    if all {
+        let markers = find_markers(reader, window_size)?;
+        for marker in &markers {
+            log::info!("Marker detected at {marker}");
+        }
+        log::info!("Detected {} markers", markers.len());
+    } else {
+        let marker = find_marker(reader, window_size)?;
+        log::info!("First start-of-packet marker detected at {marker:?}");
+    }
+
+    Ok(())
+}
 
-    let mut first_index: Option<usize> = None;
-    for (idx, value) in chars.windows(window_size).enumerate() {
-        let set: HashSet<_> = HashSet::from_iter(value);
+/// Decodes the datastream from the file, from a single connection accepted on `listen` or
+/// from stdin, logging every marker as soon as it is detected.
+pub fn solve_stream(
+    path: Option<PathBuf>,
+    listen: Option<String>,
+    packet_size: usize,
+    message_size: usize,
+) -> Result<()> {
+    let reader: Box<dyn Read> = match (path, listen) {
+        (Some(_), Some(_)) => anyhow::bail!("Either read from a file or listen, not both"),
+        (Some(path), None) => Box::new(File::open(path)?),
+        (None, Some(address)) => {
+            let listener = TcpListener::bind(address)?;
+            log::info!("Listening on {}", listener.local_addr()?);
 
-        log::debug!("Hash set: {set:?}");
+            let (stream, peer) = listener.accept()?;
+            log::info!("Decoding the datastream sent by {peer}");
 
-        if set.len() == window_size {
-            first_index = Some(idx);
-            break;
+            Box::new(stream)
         }
-    }
+        (None, None) => Box::new(std::io::stdin()),
+    };
 
-    log::info!(
-        "First start-of-packet marker detected at {:?}",
-        first_index.map(|idx| idx + window_size)
-    );
+    for event in Decoder::new(reader, packet_size, message_size)? {
+        log::info!("{}", event?);
+    }
 
     Ok(())
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkerKind {
+    Packet,
+    Message,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarkerEvent {
+    pub kind: MarkerKind,
+    /// Amount of datastream bytes processed once the marker was complete, line terminators
+    /// not being counted
+    pub position: usize,
+}
+
+impl Display for MarkerEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.kind {
+            MarkerKind::Packet => "start-of-packet",
+            MarkerKind::Message => "start-of-message",
+        };
+
+        write!(f, "First {kind} marker detected at {}", self.position)
+    }
+}
+
+/// Reads a datastream incrementally, yielding the first start-of-packet and the first
+/// start-of-message marker as soon as they are complete. Stops reading once both have
+/// been detected.
+pub struct Decoder<R> {
+    bytes: Bytes<BufReader<R>>,
+    packet: Option<MarkerDetector>,
+    message: Option<MarkerDetector>,
+    pending: Option<MarkerEvent>,
+}
+
+impl<R: Read> Decoder<R> {
+    pub fn new(reader: R, packet_size: usize, message_size: usize) -> Result<Self> {
+        Ok(Self {
+            bytes: BufReader::new(reader).bytes(),
+            packet: Some(MarkerDetector::new(packet_size)?),
+            message: Some(MarkerDetector::new(message_size)?),
+            pending: None,
+        })
+    }
+}
+
+impl<R: Read> Iterator for Decoder<R> {
+    type Item = Result<MarkerEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(event) = self.pending.take() {
+            return Some(Ok(event));
+        }
+
+        while self.packet.is_some() || self.message.is_some() {
+            let byte = match self.bytes.next()? {
+                Ok(byte) => byte,
+                Err(err) => return Some(Err(err.into())),
+            };
+
+            // Line terminators split a transmission into lines but are no signal
+            if is_line_terminator(byte) {
+                continue;
+            }
+
+            let mut events = vec![];
+            for (kind, slot) in [
+                (MarkerKind::Packet, &mut self.packet),
+                (MarkerKind::Message, &mut self.message),
+            ] {
+                if let Some(position) = slot.as_mut().and_then(|detector| detector.push(byte)) {
+                    events.push(MarkerEvent { kind, position });
+                    *slot = None;
+                }
+            }
+
+            // Both markers may be completed by the same byte, the second one is then
+            // yielded by the following call without reading any further
+            let mut events = events.into_iter();
+            if let Some(event) = events.next() {
+                self.pending = events.next();
+
+                return Some(Ok(event));
+            }
+        }
+
+        None
+    }
+}
+
+/// Tracks the last `window_size` bytes of a datastream along with how often each byte
+/// occurs within them, so that every byte is processed in constant time.
+#[derive(Debug, Clone)]
+pub struct MarkerDetector {
+    window_size: usize,
+    window: VecDeque<u8>,
+    counts: [usize; 256],
+    /// Amount of byte values occurring more than once within the window
+    duplicates: usize,
+    position: usize,
+}
+
+impl MarkerDetector {
+    pub fn new(window_size: usize) -> Result<Self> {
+        if window_size == 0 {
+            anyhow::bail!("Window size has to be at least 1");
+        }
+
+        Ok(Self {
+            window_size,
+            window: VecDeque::with_capacity(window_size + 1),
+            counts: [0; 256],
+            duplicates: 0,
+            position: 0,
+        })
+    }
+
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    /// Amount of bytes processed so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Processes the next byte, returning the amount of bytes processed if the last
+    /// `window_size` bytes are all different.
+    pub fn push(&mut self, byte: u8) -> Option<usize> {
+        self.position += 1;
+
+        self.window.push_back(byte);
+        self.counts[usize::from(byte)] += 1;
+        if self.counts[usize::from(byte)] == 2 {
+            self.duplicates += 1;
+        }
+
+        if self.window.len() > self.window_size {
+            let removed = self.window.pop_front().expect("Window is not empty");
+            self.counts[usize::from(removed)] -= 1;
+            if self.counts[usize::from(removed)] == 1 {
+                self.duplicates -= 1;
+            }
+        }
+
+        (self.window.len() == self.window_size && self.duplicates == 0).then_some(self.position)
+    }
+}
+
+/// Whether the byte terminates a line rather than belonging to the datastream. Line
+/// terminators are skipped, so that the lines of the input form a single datastream.
+fn is_line_terminator(byte: u8) -> bool {
+    matches!(byte, b'\n' | b'\r')
+}
+
+/// Returns the number of characters processed once the first `window_size` distinct
+/// characters have been seen, consuming the datastream byte by byte. Line terminators
+/// are skipped and not counted, like the [`Decoder`] does.
+pub fn find_marker<R: BufRead>(reader: R, window_size: usize) -> Result<Option<usize>> {
+    let mut detector = MarkerDetector::new(window_size)?;
+
+    for byte in reader
+        .bytes()
+        .filter(|byte| !byte.as_ref().is_ok_and(|&byte| is_line_terminator(byte)))
+    {
+        if let Some(marker) = detector.push(byte?) {
+            return Ok(Some(marker));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Returns the offset after every window of `window_size` distinct characters, in
+/// ascending order. Line terminators are skipped like in [`find_marker`].
+pub fn find_markers<R: BufRead>(reader: R, window_size: usize) -> Result<Vec<usize>> {
+    let mut detector = MarkerDetector::new(window_size)?;
+
+    reader
+        .bytes()
+        .filter(|byte| !byte.as_ref().is_ok_and(|&byte| is_line_terminator(byte)))
+        .filter_map(|byte| match byte {
+            Ok(byte) => detector.push(byte).map(Ok),
+            Err(err) => Some(Err(err.into())),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_FILE: &str = include_str!("../tasks/day6_dev.txt");
+
+    #[test]
+    fn test_find_marker() {
+        assert_eq!(find_marker(TEST_FILE.as_bytes(), 4).unwrap(), Some(11));
+        assert_eq!(find_marker(TEST_FILE.as_bytes(), 14).unwrap(), Some(26));
+        assert_eq!(find_marker("aaaa".as_bytes(), 2).unwrap(), None);
+        assert_eq!(find_marker("abc\nd".as_bytes(), 4).unwrap(), Some(4));
+        assert_eq!(find_marker("abc\n".as_bytes(), 4).unwrap(), None);
+        assert!(find_marker(TEST_FILE.as_bytes(), 0).is_err());
+    }
+
+    #[test]
+    fn test_find_markers() {
+        assert_eq!(
+            find_markers("abcabbcda".as_bytes(), 3).unwrap(),
+            vec![3, 4, 5, 8, 9]
+        );
+        assert_eq!(find_markers("aab".as_bytes(), 3).unwrap(), vec![]);
+
+        let markers = find_markers(TEST_FILE.as_bytes(), 14).unwrap();
+        assert_eq!(markers.first(), Some(&26));
+        assert!(markers.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(markers
+            .iter()
+            .all(|&marker| marker <= TEST_FILE.trim_end().len()));
+
+        assert_eq!(find_markers("abc\r\n".as_bytes(), 2).unwrap(), vec![2, 3]);
+        assert!(find_markers(TEST_FILE.as_bytes(), 0).is_err());
+    }
+
+    fn decode<R: Read>(reader: R, packet_size: usize, message_size: usize) -> Vec<MarkerEvent> {
+        Decoder::new(reader, packet_size, message_size)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_decoder() {
+        assert_eq!(
+            decode(TEST_FILE.as_bytes(), 4, 14),
+            vec![
+                MarkerEvent {
+                    kind: MarkerKind::Packet,
+                    position: 11
+                },
+                MarkerEvent {
+                    kind: MarkerKind::Message,
+                    position: 26
+                }
+            ]
+        );
+        assert_eq!(
+            decode("abcd".as_bytes(), 4, 4),
+            vec![
+                MarkerEvent {
+                    kind: MarkerKind::Packet,
+                    position: 4
+                },
+                MarkerEvent {
+                    kind: MarkerKind::Message,
+                    position: 4
+                }
+            ]
+        );
+        assert_eq!(decode("aaaa".as_bytes(), 2, 3), vec![]);
+        assert_eq!(
+            decode("ab\r\ncd\n".as_bytes(), 4, 5),
+            vec![MarkerEvent {
+                kind: MarkerKind::Packet,
+                position: 4
+            }]
+        );
+    }
+
+    #[test]
+    fn test_line_terminators() {
+        for input in ["ab\ncd\n", "ab\r\ncd\r\nabcd\n", "aab\nbcc\ndd\n"] {
+            for size in 1..=4 {
+                let decoded = decode(input.as_bytes(), size, size)
+                    .first()
+                    .map(|event| event.position);
+                assert_eq!(find_marker(input.as_bytes(), size).unwrap(), decoded);
+                assert_eq!(
+                    find_markers(input.as_bytes(), size)
+                        .unwrap()
+                        .first()
+                        .copied(),
+                    decoded
+                );
+            }
+        }
+
+        assert_eq!(find_marker("ab\ncd\n".as_bytes(), 4).unwrap(), Some(4));
+    }
+
+    #[test]
+    fn test_decoder_sizes() {
+        assert!(Decoder::new(TEST_FILE.as_bytes(), 0, 14).is_err());
+        assert!(Decoder::new(TEST_FILE.as_bytes(), 4, 0).is_err());
+    }
+
+    #[test]
+    fn test_decoder_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        // The connection stays open, so the decoder has to stop on its own once both
+        // markers have been detected
+        let sender = std::thread::spawn(move || {
+            let mut stream = std::net::TcpStream::connect(address).unwrap();
+            std::io::Write::write_all(&mut stream, TEST_FILE.as_bytes()).unwrap();
+
+            stream
+        });
+
+        let (stream, _) = listener.accept().unwrap();
+        let events = decode(stream, 4, 14);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].position, 26);
+
+        drop(sender.join().unwrap());
+    }
+}
@@ -1,139 +1,1109 @@
 use std::{
-    collections::{BTreeMap, HashMap},
-    path::PathBuf,
-    str::FromStr,
-    sync::{Arc, Mutex},
+    cmp::Reverse,
+    collections::BTreeMap,
+    fmt::Display,
+    fs::File,
+    io::{BufWriter, Write},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
 };
 
 use anyhow::Result;
 use chumsky::prelude::*;
 
-pub(crate) fn solve(path: PathBuf) -> Result<()> {
+pub fn solve(path: PathBuf, max_size: usize, device: Device) -> Result<()> {
     let file = std::fs::read_to_string(path)?;
 
-    let command_outputs = file_parser()
-        .parse(file.as_str())
-        .map_err(|err| anyhow::anyhow!("An error occurred while parsing the file: {err:?}"))?;
+    let command_outputs = parse_file(&file)?;
     log::trace!("Parsed file: {:?}", command_outputs);
 
-    let mut cwd = Arc::new(Mutex::new(PathBuf::from_str("/")?));
-    let mut individual_folder_file_size_map = BTreeMap::new();
+    let fs = VirtualFs::from_commands(&command_outputs);
+    let sizes = fs.sizes();
+    log::debug!(
+        "Directory sizes: {:#?}",
+        fs.directories()
+            .map(|id| (fs.path(id), sizes[id.0]))
+            .collect::<Vec<_>>()
+    );
 
-    for output in command_outputs {
-        match output {
-            CommandOutput::Cd(dir_name) => {
-                match dir_name.as_str() {
-                    "/" => {
-                        cwd = Arc::new(Mutex::new(PathBuf::from_str("/")?));
+    let task_a = fs.small_directories_size(&sizes, max_size);
+    log::info!("Task a solution: {task_a}");
+
+    let additional_free_space_needed = device.space_needed(sizes[fs.root().0])?;
+    log::debug!("additional_free_space_needed: {additional_free_space_needed}");
+
+    let task_b = fs
+        .directories()
+        .map(|id| sizes[id.0])
+        .filter(|&size| size >= additional_free_space_needed)
+        .min()
+        .unwrap_or_default();
+    log::info!("Task b solution: {task_b:?}");
+
+    Ok(())
+}
+
+/// Prints which directories would be deleted to make room for the update, without
+/// deleting anything.
+pub fn solve_plan(path: PathBuf, device: Device) -> Result<()> {
+    let file = std::fs::read_to_string(path)?;
+
+    let fs = VirtualFs::from_commands(&parse_file(&file)?);
+    let sizes = fs.sizes();
+
+    let plan = fs.plan_deletion(&sizes, device)?;
+    log::debug!("Plan: {plan:?}");
+
+    let mut writer = BufWriter::new(std::io::stdout().lock());
+    fs.write_plan(&mut writer, &sizes, &plan)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Total size of the puzzle's disk.
+pub const DISK_SIZE: usize = 70000000;
+/// Space required by the puzzle's update.
+pub const UPDATE_SIZE: usize = 30000000;
+
+/// Sizes of the disk and of the update to install on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Device {
+    pub disk_size: usize,
+    pub update_size: usize,
+}
+
+impl Default for Device {
+    fn default() -> Self {
+        Self {
+            disk_size: DISK_SIZE,
+            update_size: UPDATE_SIZE,
+        }
+    }
+}
+
+impl Device {
+    /// Space which has to be freed before the update fits next to the `used` space.
+    pub fn space_needed(&self, used: usize) -> Result<usize> {
+        if self.update_size > self.disk_size {
+            anyhow::bail!(
+                "The update of size {} does not fit on a disk of size {}",
+                self.update_size,
+                self.disk_size
+            );
+        }
+
+        let space_left = self.disk_size.saturating_sub(used);
+        log::debug!("Space left on device: {space_left}");
+
+        Ok(self.update_size.saturating_sub(space_left))
+    }
+}
+
+/// Inconsistency found while replaying a transcript, commands being numbered from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    AboveRoot {
+        command: usize,
+    },
+    KindConflict {
+        command: usize,
+        path: String,
+    },
+    SizeConflict {
+        command: usize,
+        path: String,
+        previous: usize,
+        listed: usize,
+    },
+    PwdMismatch {
+        command: usize,
+        printed: String,
+        actual: String,
+    },
+    UnknownCommand {
+        command: usize,
+        name: String,
+    },
+    UnknownDirectory {
+        command: usize,
+    },
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Warning::AboveRoot { command } => {
+                write!(
+                    f,
+                    "Command {command}: Can not change above the root directory"
+                )
+            }
+            Warning::KindConflict { command, path } => write!(
+                f,
+                "Command {command}: {path} is both a file and a directory"
+            ),
+            Warning::SizeConflict {
+                command,
+                path,
+                previous,
+                listed,
+            } => write!(
+                f,
+                "Command {command}: File {path} is listed with size {listed} after {previous}"
+            ),
+            Warning::PwdMismatch {
+                command,
+                printed,
+                actual,
+            } => write!(
+                f,
+                "Command {command}: pwd printed {printed} while being in {actual}"
+            ),
+            Warning::UnknownCommand { command, name } => {
+                write!(f, "Command {command}: Skipped unknown command {name:?}")
+            }
+            Warning::UnknownDirectory { command } => write!(
+                f,
+                "Command {command}: Skipped as the current directory is unknown"
+            ),
+        }
+    }
+}
+
+/// Directories to delete so that the update fits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeletionPlan {
+    pub space_needed: usize,
+    /// Directories to delete, none of them containing another one
+    pub directories: Vec<NodeId>,
+    pub freed: usize,
+}
+
+/// Prints the reconstructed filesystem: every directory within the size bounds, the
+/// tree if requested and the largest directories and files if `largest` is given.
+pub fn solve_report(
+    path: PathBuf,
+    tree: bool,
+    largest: Option<usize>,
+    filter: SizeFilter,
+) -> Result<()> {
+    let file = std::fs::read_to_string(path)?;
+
+    let fs = VirtualFs::from_commands(&parse_file(&file)?);
+    let sizes = fs.sizes();
+
+    let mut writer = BufWriter::new(std::io::stdout().lock());
+
+    if tree {
+        fs.write_tree(&mut writer, &sizes, filter.min)?;
+        writeln!(writer)?;
+    }
+
+    let directories: Vec<_> = fs
+        .directories()
+        .filter(|id| filter.contains(sizes[id.0]))
+        .collect();
+    fs.write_du(&mut writer, &sizes, &directories)?;
+
+    if let Some(n) = largest {
+        writeln!(writer, "\nLargest directories:")?;
+        fs.write_du(
+            &mut writer,
+            &sizes,
+            &fs.largest(fs.directories(), &sizes, filter, n),
+        )?;
+
+        writeln!(writer, "\nLargest files:")?;
+        fs.write_du(
+            &mut writer,
+            &sizes,
+            &fs.largest(fs.files(), &sizes, filter, n),
+        )?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Inclusive bounds on the size of the nodes to report.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SizeFilter {
+    pub min: Option<usize>,
+    pub max: Option<usize>,
+}
+
+impl SizeFilter {
+    pub fn contains(&self, size: usize) -> bool {
+        self.min.is_none_or(|min| size >= min) && self.max.is_none_or(|max| size <= max)
+    }
+}
+
+/// Formats a size in bytes like `du -h`, using powers of 1024.
+pub fn human_size(size: usize) -> String {
+    const UNITS: [&str; 5] = ["K", "M", "G", "T", "P"];
+
+    if size < 1024 {
+        return size.to_string();
+    }
+
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{value:.1}{}", UNITS[unit])
+}
+
+/// Writes the reconstructed filesystem as JSON to the output file or to stdout.
+pub fn solve_export(path: PathBuf, output: Option<PathBuf>) -> Result<()> {
+    let file = std::fs::read_to_string(path)?;
+
+    let fs = VirtualFs::from_commands(&parse_file(&file)?);
+
+    let mut writer: BufWriter<Box<dyn Write>> = match output {
+        Some(output) => BufWriter::new(Box::new(File::create(output)?)),
+        None => BufWriter::new(Box::new(std::io::stdout().lock())),
+    };
+    fs.write_json(&mut writer)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Walks a real directory and writes a transcript exploring it to the output file or to
+/// stdout, after checking that the transcript reproduces the directory.
+pub fn solve_transcript(directory: PathBuf, output: Option<PathBuf>) -> Result<()> {
+    let fs = VirtualFs::from_directory(&directory)?;
+    log::info!("Found {} files and directories", fs.len() - 1);
+
+    let mut transcript = vec![];
+    fs.write_transcript(&mut transcript)?;
+    let transcript = String::from_utf8(transcript)?;
+
+    let (replayed, warnings) = VirtualFs::interpret(&parse_file(&transcript)?);
+    if !warnings.is_empty() || replayed.json()? != fs.json()? {
+        anyhow::bail!("The transcript does not reproduce {directory:?}");
+    }
+
+    match output {
+        Some(output) => std::fs::write(output, transcript)?,
+        None => print!("{transcript}"),
+    }
+
+    Ok(())
+}
+
+/// Pending output of [`VirtualFs::write_json`], which walks the tree with an explicit
+/// stack to handle arbitrarily deep trees.
+enum JsonStep {
+    /// Writes a node after the separator, opening the children of a directory.
+    Open(NodeId, usize, &'static str),
+    /// Closes the children of a directory once they are written.
+    Close(NodeId, usize),
+}
+
+/// Writes a string as a JSON string literal, escaping quotes, backslashes and control
+/// characters.
+fn write_json_string<W: Write>(writer: &mut W, value: &str) -> Result<()> {
+    write!(writer, "\"")?;
+
+    for char in value.chars() {
+        match char {
+            '"' => write!(writer, "\\\"")?,
+            '\\' => write!(writer, "\\\\")?,
+            '\n' => write!(writer, "\\n")?,
+            '\r' => write!(writer, "\\r")?,
+            '\t' => write!(writer, "\\t")?,
+            char if char.is_control() => write!(writer, "\\u{:04x}", char as u32)?,
+            char => write!(writer, "{char}")?,
+        }
+    }
+
+    write!(writer, "\"")?;
+
+    Ok(())
+}
+
+pub fn parse_file(file: &str) -> Result<Vec<CommandOutput>> {
+    file_parser()
+        .parse(file)
+        .map_err(|err| anyhow::anyhow!("An error occurred while parsing the file: {err:?}"))
+}
+
+// --- Filesystem ---
+
+/// Index of a node within a [`VirtualFs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(pub usize);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeKind {
+    /// Children indexed by their name
+    Directory(BTreeMap<String, NodeId>),
+    File(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub name: String,
+    pub parent: Option<NodeId>,
+    pub kind: NodeKind,
+}
+
+impl Node {
+    pub fn is_directory(&self) -> bool {
+        matches!(self.kind, NodeKind::Directory(_))
+    }
+}
+
+/// Filesystem tree reconstructed from a terminal transcript. Nodes are stored in the
+/// order they were discovered, so that every node comes after its parent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VirtualFs {
+    nodes: Vec<Node>,
+}
+
+impl Default for VirtualFs {
+    fn default() -> Self {
+        Self {
+            nodes: vec![Node {
+                name: "/".to_owned(),
+                parent: None,
+                kind: NodeKind::Directory(BTreeMap::new()),
+            }],
+        }
+    }
+}
+
+impl VirtualFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replays the commands starting at the root directory, logging every inconsistency.
+    pub fn from_commands(command_outputs: &[CommandOutput]) -> Self {
+        let (fs, warnings) = Self::interpret(command_outputs);
+        for warning in &warnings {
+            log::warn!("{warning}");
+        }
+
+        fs
+    }
+
+    /// Replays the commands starting at the root directory. Repeated listings of a
+    /// directory are merged, later sizes of a file replacing earlier ones. Inconsistent
+    /// listings, `cd ..` above the root and unknown commands are skipped with a warning.
+    pub fn interpret(command_outputs: &[CommandOutput]) -> (Self, Vec<Warning>) {
+        let mut fs = Self::new();
+        // Unknown after changing into a file, until an absolute path leads out again
+        let mut cwd = Some(fs.root());
+        let mut warnings = vec![];
+
+        for (index, output) in command_outputs.iter().enumerate() {
+            let command = index + 1;
+
+            match output {
+                CommandOutput::Cd(target) => {
+                    cwd = fs.resolve(cwd, target, command, &mut warnings);
+
+                    log::trace!("Current cwd: {:?}", cwd.map(|cwd| fs.path(cwd)));
+                }
+                CommandOutput::Ls(entries) => {
+                    let Some(cwd) = cwd else {
+                        warnings.push(Warning::UnknownDirectory { command });
+                        continue;
+                    };
+
+                    for entry in entries {
+                        if let Err(warning) = fs.list(cwd, entry, command) {
+                            warnings.push(warning);
+                        }
                     }
-                    ".." => {
-                        cwd.lock().unwrap().pop();
+                }
+                CommandOutput::Pwd(printed) => {
+                    let Some(cwd) = cwd else {
+                        warnings.push(Warning::UnknownDirectory { command });
+                        continue;
+                    };
+                    let actual = fs.path(cwd);
+
+                    match printed {
+                        Some(printed) if *printed != actual => {
+                            warnings.push(Warning::PwdMismatch {
+                                command,
+                                printed: printed.clone(),
+                                actual,
+                            })
+                        }
+                        _ => log::debug!("pwd: {actual}"),
                     }
-                    dir_name => {
-                        cwd.lock().unwrap().push(dir_name);
+                }
+                CommandOutput::Unknown(name) => warnings.push(Warning::UnknownCommand {
+                    command,
+                    name: name.clone(),
+                }),
+            }
+        }
+
+        (fs, warnings)
+    }
+
+    /// Follows an absolute or relative path of directories, creating missing ones. `..`
+    /// above the root stays at the root, while a path through a file, or a relative path
+    /// from an unknown directory, leads to an unknown directory.
+    fn resolve(
+        &mut self,
+        cwd: Option<NodeId>,
+        target: &str,
+        command: usize,
+        warnings: &mut Vec<Warning>,
+    ) -> Option<NodeId> {
+        let mut directory = if target.starts_with('/') {
+            self.root()
+        } else {
+            let Some(cwd) = cwd else {
+                warnings.push(Warning::UnknownDirectory { command });
+                return None;
+            };
+            cwd
+        };
+
+        for segment in target.split('/').filter(|segment| !segment.is_empty()) {
+            directory = match segment {
+                "." => directory,
+                ".." => self.parent(directory).unwrap_or_else(|| {
+                    warnings.push(Warning::AboveRoot { command });
+                    directory
+                }),
+                name => match self.add_directory(directory, name) {
+                    Ok(child) => child,
+                    Err(_) => {
+                        warnings.push(Warning::KindConflict {
+                            command,
+                            path: self.child_path(directory, name),
+                        });
+                        return None;
                     }
+                },
+            };
+        }
+
+        Some(directory)
+    }
+
+    /// Adds a listed entry to `cwd`. Listing a file again replaces its size and warns with
+    /// a [`Warning::SizeConflict`] if it differs.
+    fn list(&mut self, cwd: NodeId, entry: &DirectoryEntry, command: usize) -> Result<(), Warning> {
+        let kind_conflict = |fs: &Self, name: &str| Warning::KindConflict {
+            command,
+            path: fs.child_path(cwd, name),
+        };
+
+        match entry {
+            DirectoryEntry::Directory(name) => {
+                self.add_directory(cwd, name)
+                    .map_err(|_| kind_conflict(self, name))?;
+            }
+            DirectoryEntry::File(name, size) => {
+                let id = self
+                    .add_file(cwd, name, *size)
+                    .map_err(|_| kind_conflict(self, name))?;
+
+                let NodeKind::File(previous) = &mut self.nodes[id.0].kind else {
+                    unreachable!("Added node is a file");
                 };
+                let previous = std::mem::replace(previous, *size);
 
-                log::trace!("Current cwd: {:?}", cwd.lock().unwrap().to_str());
+                if previous != *size {
+                    return Err(Warning::SizeConflict {
+                        command,
+                        path: self.path(id),
+                        previous,
+                        listed: *size,
+                    });
+                }
             }
-            CommandOutput::Ls(entries) => {
-                let current_cwd = cwd.lock().unwrap().to_str().unwrap().to_owned();
-
-                let folder_size: usize = entries
-                    .into_iter()
-                    .filter_map(|entry| {
-                        if let DirectoryEntry::File(_, size) = entry {
-                            Some(size)
-                        } else {
-                            None
-                        }
-                    })
-                    .sum();
+        }
+
+        Ok(())
+    }
+
+    pub fn root(&self) -> NodeId {
+        NodeId(0)
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.0]
+    }
 
-                log::trace!("current_cwd: {current_cwd:?} --> {folder_size}");
-                individual_folder_file_size_map.insert(current_cwd, folder_size);
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn parent(&self, id: NodeId) -> Option<NodeId> {
+        self.node(id).parent
+    }
+
+    /// Children of a directory sorted by their name, files having none.
+    pub fn children(&self, id: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        let children = match &self.node(id).kind {
+            NodeKind::Directory(children) => Some(children.values().copied()),
+            NodeKind::File(_) => None,
+        };
+
+        children.into_iter().flatten()
+    }
+
+    pub fn child(&self, id: NodeId, name: &str) -> Option<NodeId> {
+        match &self.node(id).kind {
+            NodeKind::Directory(children) => children.get(name).copied(),
+            NodeKind::File(_) => None,
+        }
+    }
+
+    /// Returns the directory of that name within `parent`, creating it if necessary.
+    pub fn add_directory(&mut self, parent: NodeId, name: &str) -> Result<NodeId> {
+        match self.child(parent, name) {
+            Some(id) if self.node(id).is_directory() => Ok(id),
+            Some(_) => anyhow::bail!(
+                "{} is a file, not a directory",
+                self.child_path(parent, name)
+            ),
+            None => self.insert(parent, name, NodeKind::Directory(BTreeMap::new())),
+        }
+    }
+
+    /// Adds a file to `parent`, or returns the file of that name added before without
+    /// touching its size.
+    pub fn add_file(&mut self, parent: NodeId, name: &str, size: usize) -> Result<NodeId> {
+        match self.child(parent, name) {
+            Some(id) if !self.node(id).is_directory() => Ok(id),
+            Some(_) => anyhow::bail!(
+                "{} is a directory, not a file",
+                self.child_path(parent, name)
+            ),
+            None => self.insert(parent, name, NodeKind::File(size)),
+        }
+    }
+
+    fn insert(&mut self, parent: NodeId, name: &str, kind: NodeKind) -> Result<NodeId> {
+        let id = NodeId(self.nodes.len());
+
+        let NodeKind::Directory(children) = &mut self.nodes[parent.0].kind else {
+            anyhow::bail!("{} is not a directory", self.path(parent));
+        };
+        children.insert(name.to_owned(), id);
+
+        self.nodes.push(Node {
+            name: name.to_owned(),
+            parent: Some(parent),
+            kind,
+        });
+
+        Ok(id)
+    }
+
+    /// Absolute path of the node, e.g. `/a/e`.
+    pub fn path(&self, id: NodeId) -> String {
+        let mut names: Vec<&str> = std::iter::successors(Some(id), |&id| self.parent(id))
+            .take_while(|&id| id != self.root())
+            .map(|id| self.node(id).name.as_str())
+            .collect();
+        names.reverse();
+
+        format!("/{}", names.join("/"))
+    }
+
+    fn child_path(&self, parent: NodeId, name: &str) -> String {
+        format!("{}/{name}", self.path(parent).trim_end_matches('/'))
+    }
+
+    pub fn directories(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.ids().filter(|&id| self.node(id).is_directory())
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.ids().filter(|&id| !self.node(id).is_directory())
+    }
+
+    fn ids(&self) -> impl Iterator<Item = NodeId> {
+        (0..self.nodes.len()).map(NodeId)
+    }
+
+    /// Total size of every node indexed by its id, directories including everything
+    /// below them. Computed in a single pass as children always follow their parent.
+    pub fn sizes(&self) -> Vec<usize> {
+        let mut sizes: Vec<usize> = self
+            .nodes
+            .iter()
+            .map(|node| match node.kind {
+                NodeKind::File(size) => size,
+                NodeKind::Directory(_) => 0,
+            })
+            .collect();
+
+        for (index, node) in self.nodes.iter().enumerate().rev() {
+            if let Some(parent) = node.parent {
+                sizes[parent.0] += sizes[index];
             }
         }
+
+        sizes
     }
 
-    log::debug!("folder_size_map: {individual_folder_file_size_map:#?}");
+    /// Sums up the sizes of the directories of at most `max_size`, a directory of exactly
+    /// `max_size` being included.
+    pub fn small_directories_size(&self, sizes: &[usize], max_size: usize) -> usize {
+        self.directories()
+            .map(|id| sizes[id.0])
+            .filter(|&size| size <= max_size)
+            .sum()
+    }
+
+    /// The `n` largest of the given nodes within the bounds, from the largest downwards.
+    pub fn largest(
+        &self,
+        ids: impl Iterator<Item = NodeId>,
+        sizes: &[usize],
+        filter: SizeFilter,
+        n: usize,
+    ) -> Vec<NodeId> {
+        let mut ids: Vec<_> = ids.filter(|id| filter.contains(sizes[id.0])).collect();
+        ids.sort_by_cached_key(|id| (Reverse(sizes[id.0]), self.path(*id)));
+        ids.truncate(n);
 
-    let keys = individual_folder_file_size_map.keys();
-    log::trace!("folder keys: {keys:?}");
+        ids
+    }
 
-    let mut total_folder_size_map: HashMap<&str, usize> = HashMap::new();
-    for key_prefix in keys {
-        let total_folder_size: usize = individual_folder_file_size_map
-            .range(key_prefix.clone()..)
-            .take_while(|(key, _)| key.starts_with(key_prefix))
-            .map(|(_, v)| v)
-            .sum();
+    /// Writes one line per node holding its human-readable size and its path, like `du`.
+    pub fn write_du<W: Write>(
+        &self,
+        writer: &mut W,
+        sizes: &[usize],
+        ids: &[NodeId],
+    ) -> Result<()> {
+        for id in ids {
+            writeln!(writer, "{:<8}{}", human_size(sizes[id.0]), self.path(*id))?;
+        }
 
-        total_folder_size_map.insert(key_prefix, total_folder_size);
+        Ok(())
     }
-    log::debug!("total_folder_size_map: {total_folder_size_map:#?}");
 
-    let task_a: usize = total_folder_size_map
-        .values()
-        .filter(|&&size| size < 100000)
-        .sum();
-    log::info!("Task a solution: {task_a}");
+    /// Writes the tree in the puzzle's format, leaving out nodes smaller than `min_size`
+    /// along with everything below them.
+    pub fn write_tree<W: Write>(
+        &self,
+        writer: &mut W,
+        sizes: &[usize],
+        min_size: Option<usize>,
+    ) -> Result<()> {
+        let mut stack = vec![(self.root(), 0)];
 
-    let space_left = total_folder_size_map
-        .get("/")
-        .map(|used| 70000000 - used)
-        .unwrap_or_default();
-    log::debug!("Space left on device: {space_left}");
+        while let Some((id, depth)) = stack.pop() {
+            if min_size.is_some_and(|min| sizes[id.0] < min) {
+                continue;
+            }
 
-    let space_needed_for_update = 30000000;
-    let additional_free_space_needed = space_needed_for_update - space_left;
-    log::debug!("additional_free_space_needed: {additional_free_space_needed}");
+            let node = self.node(id);
+            let kind = if node.is_directory() { "dir" } else { "file" };
+            writeln!(
+                writer,
+                "{:indent$}- {} ({kind}, {})",
+                "",
+                node.name,
+                human_size(sizes[id.0]),
+                indent = depth * 2
+            )?;
 
-    let task_b = total_folder_size_map
-        .values()
-        .copied()
-        .filter(|&size| size >= additional_free_space_needed)
-        .min()
-        .unwrap_or_default();
-    log::info!("Task b solution: {task_b:?}");
+            let children: Vec<_> = self.children(id).collect();
+            stack.extend(children.into_iter().rev().map(|child| (child, depth + 1)));
+        }
 
-    Ok(())
+        Ok(())
+    }
+
+    pub fn is_ancestor(&self, ancestor: NodeId, id: NodeId) -> bool {
+        std::iter::successors(Some(id), |&id| self.parent(id)).any(|id| id == ancestor)
+    }
+
+    /// Finds the fewest directories whose deletion frees enough space for the update,
+    /// then frees as little space as it can with that many. The root is never deleted.
+    ///
+    /// Deleting a directory frees at least as much as deleting anything within it, so the
+    /// largest directories within the root free the most space any amount of directories
+    /// can and give the fewest directories exactly. Freeing the least space with them is a
+    /// subset sum though, so the plan is only improved one swapped directory at a time.
+    pub fn plan_deletion(&self, sizes: &[usize], device: Device) -> Result<DeletionPlan> {
+        let space_needed = device.space_needed(sizes[self.root().0])?;
+
+        let mut top_level: Vec<_> = self
+            .children(self.root())
+            .filter(|&id| self.node(id).is_directory())
+            .collect();
+        top_level.sort_by_key(|id| Reverse(sizes[id.0]));
+
+        let mut directories = vec![];
+        let mut freed = 0;
+        for id in top_level {
+            if freed >= space_needed {
+                break;
+            }
+            directories.push(id);
+            freed += sizes[id.0];
+        }
+        if freed < space_needed {
+            anyhow::bail!("Deleting directories can not free {space_needed} of space");
+        }
+
+        let candidates: Vec<_> = self.directories().filter(|&id| id != self.root()).collect();
+        let ranges = self.subtree_ranges();
+        let overlaps = |a: NodeId, b: NodeId| {
+            ranges[a.0].contains(ranges[b.0].start()) || ranges[b.0].contains(ranges[a.0].start())
+        };
+
+        // Every round frees strictly less, bounding the rounds keeps the worst case polynomial
+        for _ in 0..candidates.len() {
+            let mut best = None;
+            for (index, &old) in directories.iter().enumerate() {
+                let rest = freed - sizes[old.0];
+                for &id in &candidates {
+                    let swapped = rest + sizes[id.0];
+                    if swapped < space_needed || swapped >= best.map_or(freed, |(_, _, best)| best)
+                    {
+                        continue;
+                    }
+
+                    let fits = directories
+                        .iter()
+                        .enumerate()
+                        .all(|(other, &kept)| other == index || !overlaps(kept, id));
+                    if fits {
+                        best = Some((index, id, swapped));
+                    }
+                }
+            }
+
+            let Some((index, id, swapped)) = best else {
+                break;
+            };
+            directories[index] = id;
+            freed = swapped;
+        }
+
+        directories.sort_by_cached_key(|&id| (Reverse(sizes[id.0]), self.path(id)));
+
+        Ok(DeletionPlan {
+            space_needed,
+            directories,
+            freed,
+        })
+    }
+
+    /// Pre-order position of every node up to the last position within it, so a node lies
+    /// within another exactly when its position falls in the other's range.
+    fn subtree_ranges(&self) -> Vec<RangeInclusive<usize>> {
+        let mut first = vec![0; self.nodes.len()];
+        let mut stack = vec![self.root()];
+        for position in 0.. {
+            let Some(id) = stack.pop() else {
+                break;
+            };
+            first[id.0] = position;
+
+            let children: Vec<_> = self.children(id).collect();
+            stack.extend(children.into_iter().rev());
+        }
+
+        // Children follow their parent, so they are done before it in reverse
+        let mut last = first.clone();
+        for (index, node) in self.nodes.iter().enumerate().rev() {
+            if let Some(parent) = node.parent {
+                last[parent.0] = last[parent.0].max(last[index]);
+            }
+        }
+
+        first
+            .into_iter()
+            .zip(last)
+            .map(|(first, last)| first..=last)
+            .collect()
+    }
+
+    /// Writes which directories would be deleted, with the amount of nodes within them.
+    pub fn write_plan<W: Write>(
+        &self,
+        writer: &mut W,
+        sizes: &[usize],
+        plan: &DeletionPlan,
+    ) -> Result<()> {
+        writeln!(writer, "Space needed: {}", human_size(plan.space_needed))?;
+
+        for &id in &plan.directories {
+            let nested = self
+                .ids()
+                .filter(|&other| other != id && self.is_ancestor(id, other));
+            let (directories, files) = nested.fold((0, 0), |(directories, files), other| {
+                if self.node(other).is_directory() {
+                    (directories + 1, files)
+                } else {
+                    (directories, files + 1)
+                }
+            });
+
+            writeln!(
+                writer,
+                "Would remove {} ({}, {directories} directories, {files} files)",
+                self.path(id),
+                human_size(sizes[id.0])
+            )?;
+        }
+
+        writeln!(
+            writer,
+            "Would free {} in {} directories",
+            human_size(plan.freed),
+            plan.directories.len()
+        )?;
+
+        Ok(())
+    }
+
+    /// Reads the files and directories below `path`, skipping symbolic links.
+    pub fn from_directory(path: &Path) -> Result<Self> {
+        let mut fs = Self::new();
+        fs.read_directory(fs.root(), path)?;
+
+        Ok(fs)
+    }
+
+    /// Adds the contents of a directory sorted by name, before descending into its
+    /// subdirectories in the same order.
+    fn read_directory(&mut self, id: NodeId, path: &Path) -> Result<()> {
+        let mut stack = vec![(id, path.to_owned())];
+        while let Some((id, path)) = stack.pop() {
+            let directories = self.read_entries(id, &path)?;
+            stack.extend(directories.into_iter().rev());
+        }
+
+        Ok(())
+    }
+
+    /// Adds the entries of a single directory, returning its subdirectories.
+    fn read_entries(&mut self, id: NodeId, path: &Path) -> Result<Vec<(NodeId, PathBuf)>> {
+        let mut entries = std::fs::read_dir(path)?.collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+
+        let mut directories = vec![];
+        for entry in entries {
+            let Some(name) = entry.file_name().to_str().map(str::to_owned) else {
+                log::warn!("Skipping {:?} as its name is not valid UTF-8", entry.path());
+                continue;
+            };
+            if name.contains(['\n', '\r', '/']) || name.trim() != name {
+                log::warn!("Skipping {:?} as its name can not be written", entry.path());
+                continue;
+            }
+
+            let metadata = std::fs::symlink_metadata(entry.path())?;
+            if metadata.is_dir() {
+                directories.push((self.add_directory(id, &name)?, entry.path()));
+            } else if metadata.is_file() {
+                self.add_file(id, &name, usize::try_from(metadata.len())?)?;
+            } else {
+                log::debug!("Skipping {:?}", entry.path());
+            }
+        }
+
+        Ok(directories)
+    }
+
+    /// Writes a transcript in the puzzle's format listing every directory, starting at
+    /// the root and returning to the parent directory after each subdirectory.
+    pub fn write_transcript<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writeln!(writer, "$ cd /")?;
+
+        // Entering a directory is followed by leaving it again, marked by `None`
+        let mut stack = vec![Some(self.root())];
+        while let Some(step) = stack.pop() {
+            let Some(id) = step else {
+                writeln!(writer, "$ cd ..")?;
+                continue;
+            };
+
+            if id != self.root() {
+                writeln!(writer, "$ cd {}", self.node(id).name)?;
+            }
+            writeln!(writer, "$ ls")?;
+
+            for child in self.children(id) {
+                let node = self.node(child);
+                match node.kind {
+                    NodeKind::Directory(_) => writeln!(writer, "dir {}", node.name)?,
+                    NodeKind::File(size) => writeln!(writer, "{size} {}", node.name)?,
+                }
+            }
+
+            let directories: Vec<_> = self
+                .children(id)
+                .filter(|&child| self.node(child).is_directory())
+                .collect();
+            for directory in directories.into_iter().rev() {
+                stack.extend([None, Some(directory)]);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the tree as nested JSON objects holding the name, type and total size of
+    /// every node, directories listing their children sorted by name.
+    pub fn write_json<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let sizes = self.sizes();
+
+        let mut stack = vec![JsonStep::Open(self.root(), 0, "")];
+        while let Some(step) = stack.pop() {
+            match step {
+                JsonStep::Open(id, depth, separator) => {
+                    let node = self.node(id);
+                    write!(writer, "{separator}{}{{\"name\": ", "  ".repeat(depth))?;
+                    write_json_string(writer, &node.name)?;
+
+                    let kind = if node.is_directory() { "dir" } else { "file" };
+                    write!(writer, ", \"type\": \"{kind}\", \"size\": {}", sizes[id.0])?;
+
+                    if !node.is_directory() {
+                        write!(writer, "}}")?;
+                        continue;
+                    }
+
+                    write!(writer, ", \"children\": [")?;
+                    stack.push(JsonStep::Close(id, depth));
+
+                    let children: Vec<_> = self.children(id).collect();
+                    for (index, child) in children.into_iter().enumerate().rev() {
+                        let separator = if index == 0 { "\n" } else { ",\n" };
+                        stack.push(JsonStep::Open(child, depth + 1, separator));
+                    }
+                }
+                JsonStep::Close(id, depth) => {
+                    if self.children(id).next().is_some() {
+                        write!(writer, "\n{}", "  ".repeat(depth))?;
+                    }
+                    write!(writer, "]}}")?;
+                }
+            }
+        }
+        writeln!(writer)?;
+
+        Ok(())
+    }
+
+    pub fn json(&self) -> Result<String> {
+        let mut json = vec![];
+        self.write_json(&mut json)?;
+
+        Ok(String::from_utf8(json)?)
+    }
 }
 
 // --- Parser ---
 
-#[derive(Debug, PartialEq)]
-enum CommandOutput {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandOutput {
     Cd(String),
     Ls(Vec<DirectoryEntry>),
+    /// Printed directory, if the output was recorded
+    Pwd(Option<String>),
+    /// Name and arguments of a command without meaning to the filesystem
+    Unknown(String),
 }
 
 fn file_parser() -> impl Parser<char, Vec<CommandOutput>, Error = Simple<char>> {
     cd_line_parser()
         .map(CommandOutput::Cd)
         .or(ls_lines_parser().map(CommandOutput::Ls))
+        .or(pwd_lines_parser().map(CommandOutput::Pwd))
+        .or(unknown_lines_parser().map(CommandOutput::Unknown))
         .repeated()
+        .then_ignore(text::whitespace())
+        .then_ignore(end())
+}
+
+/// Parses the `$` prompt followed by the name of the command.
+fn command_parser(name: &'static str) -> impl Parser<char, (), Error = Simple<char>> {
+    text::whitespace()
+        .ignore_then(just('$'))
+        .ignore_then(just(' ').repeated())
+        .ignore_then(just(name))
+        .ignored()
+}
+
+/// Parses the rest of the line, up to the newline or the end of the input.
+fn line_parser() -> impl Parser<char, String, Error = Simple<char>> {
+    take_until(text::newline().or(end())).map(|(preceding, _)| preceding.into_iter().collect())
+}
+
+/// Parses a line printed by a command, i.e. one which is not a prompt.
+fn output_line_parser() -> impl Parser<char, String, Error = Simple<char>> {
+    text::whitespace()
+        .ignore_then(none_of("$").rewind())
+        .ignore_then(line_parser())
+}
+
+fn empty_rest_parser() -> impl Parser<char, (), Error = Simple<char>> {
+    line_parser().try_map(|rest, span| {
+        if rest.trim().is_empty() {
+            Ok(())
+        } else {
+            Err(Simple::custom(
+                span,
+                format!("Unexpected arguments {rest:?}"),
+            ))
+        }
+    })
 }
 
 fn cd_line_parser() -> impl Parser<char, String, Error = Simple<char>> {
-    just("$ cd")
-        .padded()
-        .ignore_then(take_until(text::newline()))
-        .map(|(preceding, _)| preceding)
-        .collect()
+    command_parser("cd")
+        .ignore_then(just(' ').repeated().at_least(1))
+        .ignore_then(line_parser())
+        .map(|target| target.trim_end().to_owned())
         .labelled("cd")
 }
 
 fn ls_lines_parser() -> impl Parser<char, Vec<DirectoryEntry>, Error = Simple<char>> {
-    just("$ ls")
-        .padded()
-        .then(dir_line_parser().or(file_line_parser()).repeated())
-        .map(|(_, o)| o)
-        .collect()
+    command_parser("ls")
+        .ignore_then(empty_rest_parser())
+        .ignore_then(dir_line_parser().or(file_line_parser()).repeated())
+        .labelled("ls")
 }
 
-#[derive(Debug, PartialEq)]
-enum DirectoryEntry {
+fn pwd_lines_parser() -> impl Parser<char, Option<String>, Error = Simple<char>> {
+    command_parser("pwd")
+        .ignore_then(empty_rest_parser())
+        .ignore_then(output_line_parser().or_not())
+        .labelled("pwd")
+}
+
+fn unknown_lines_parser() -> impl Parser<char, String, Error = Simple<char>> {
+    text::whitespace()
+        .ignore_then(just('$'))
+        .ignore_then(line_parser())
+        .then_ignore(output_line_parser().repeated())
+        .map(|command| command.trim().to_owned())
+        .labelled("command")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DirectoryEntry {
     Directory(String),
     File(String, usize),
 }
@@ -141,9 +1111,7 @@ enum DirectoryEntry {
 fn dir_line_parser() -> impl Parser<char, DirectoryEntry, Error = Simple<char>> {
     just("dir")
         .padded()
-        .ignore_then(take_until(text::newline()))
-        .map(|(preceding, _)| preceding)
-        .collect()
+        .ignore_then(line_parser())
         .map(DirectoryEntry::Directory)
         .labelled("dir")
 }
@@ -151,13 +1119,12 @@ fn dir_line_parser() -> impl Parser<char, DirectoryEntry, Error = Simple<char>>
 fn file_line_parser() -> impl Parser<char, DirectoryEntry, Error = Simple<char>> {
     text::digits(10)
         .padded()
-        .then(take_until(text::newline()).map(|(preceding, _)| preceding))
-        .map(|(size, name)| {
-            DirectoryEntry::File(
-                name.into_iter().collect::<String>(),
-                size.parse::<usize>().unwrap(),
-            )
+        .try_map(|size: String, span| {
+            size.parse::<usize>()
+                .map_err(|err| Simple::custom(span, format!("{err}")))
         })
+        .then(line_parser())
+        .map(|(size, name)| DirectoryEntry::File(name, size))
         .labelled("file")
 }
 
@@ -257,4 +1224,317 @@ mod tests {
             ]
         );
     }
+
+    fn dev_fs() -> VirtualFs {
+        let commands = parse_file(include_str!("../tasks/day7_dev.txt")).unwrap();
+
+        VirtualFs::from_commands(&commands)
+    }
+
+    #[test]
+    fn test_virtual_fs() {
+        let fs = dev_fs();
+        let sizes = fs.sizes();
+
+        let directory_sizes: Vec<_> = fs
+            .directories()
+            .map(|id| (fs.path(id), sizes[id.0]))
+            .collect();
+        assert_eq!(
+            directory_sizes,
+            vec![
+                ("/".to_owned(), 48381165),
+                ("/a".to_owned(), 94853),
+                ("/d".to_owned(), 24933642),
+                ("/a/e".to_owned(), 584),
+            ]
+        );
+
+        let e = fs.child(fs.child(fs.root(), "a").unwrap(), "e").unwrap();
+        let i = fs.child(e, "i").unwrap();
+        assert_eq!(fs.path(i), "/a/e/i");
+        assert_eq!(fs.node(i).kind, NodeKind::File(584));
+        assert_eq!(fs.files().count(), 10);
+        assert_eq!(
+            sizes[fs.root().0],
+            fs.files().map(|id| sizes[id.0]).sum::<usize>()
+        );
+    }
+
+    #[test]
+    fn test_small_directories_size() {
+        let fs = dev_fs();
+        let sizes = fs.sizes();
+
+        assert_eq!(fs.small_directories_size(&sizes, 100000), 95437);
+        assert_eq!(fs.small_directories_size(&sizes, 94853), 95437);
+        assert_eq!(fs.small_directories_size(&sizes, 94852), 584);
+        assert_eq!(fs.small_directories_size(&sizes, 583), 0);
+    }
+
+    #[test]
+    fn test_sibling_prefixes() {
+        let commands = parse_file(
+            "$ cd /\n$ ls\ndir a\ndir ab\n$ cd a\n$ ls\n10 x\n$ cd ..\n$ cd ab\n$ ls\n20 x\n",
+        )
+        .unwrap();
+        let mut fs = VirtualFs::from_commands(&commands);
+
+        let a = fs.child(fs.root(), "a").unwrap();
+        let ab = fs.child(fs.root(), "ab").unwrap();
+        let sizes = fs.sizes();
+        assert_eq!(sizes[a.0], 10);
+        assert_eq!(sizes[ab.0], 20);
+        assert_eq!(sizes[fs.root().0], 30);
+        assert!(fs.add_directory(a, "x").is_err());
+    }
+
+    #[test]
+    fn test_reports() {
+        let fs = dev_fs();
+        let sizes = fs.sizes();
+
+        let mut tree = vec![];
+        fs.write_tree(&mut tree, &sizes, Some(10000)).unwrap();
+        assert_eq!(
+            String::from_utf8(tree).unwrap(),
+            "- / (dir, 46.1M)\n  - a (dir, 92.6K)\n    - f (file, 28.4K)\n    - h.lst (file, 61.1K)\n  - b.txt (file, 14.2M)\n  - c.dat (file, 8.1M)\n  - d (dir, 23.8M)\n    - d.ext (file, 5.4M)\n    - d.log (file, 7.7M)\n    - j (file, 3.9M)\n    - k (file, 6.9M)\n"
+        );
+
+        let filter = SizeFilter {
+            min: None,
+            max: Some(100000),
+        };
+        let small = fs.largest(fs.directories(), &sizes, filter, 5);
+        assert_eq!(
+            small.iter().map(|&id| fs.path(id)).collect::<Vec<_>>(),
+            vec!["/a", "/a/e"]
+        );
+
+        let mut du = vec![];
+        fs.write_du(
+            &mut du,
+            &sizes,
+            &fs.largest(fs.files(), &sizes, SizeFilter::default(), 2),
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(du).unwrap(),
+            "14.2M   /b.txt\n8.1M    /c.dat\n"
+        );
+
+        assert_eq!(human_size(584), "584");
+        assert_eq!(human_size(1536), "1.5K");
+    }
+
+    #[test]
+    fn test_plan_deletion() {
+        let fs = dev_fs();
+        let sizes = fs.sizes();
+
+        let plan = fs.plan_deletion(&sizes, Device::default()).unwrap();
+        assert_eq!(plan.space_needed, 8381165);
+        assert_eq!(plan.freed, 24933642);
+        assert_eq!(plan.directories, vec![fs.child(fs.root(), "d").unwrap()]);
+
+        let mut fs = VirtualFs::new();
+        let root = fs.root();
+        for (name, size) in [("x", 60), ("y", 50), ("z", 20)] {
+            let directory = fs.add_directory(root, name).unwrap();
+            fs.add_file(directory, "file", size).unwrap();
+        }
+        let z = fs.child(root, "z").unwrap();
+        let w = fs.add_directory(z, "w").unwrap();
+        fs.add_file(w, "file", 10).unwrap();
+        let sizes = fs.sizes();
+
+        let plan_for = |update_size| {
+            let device = Device {
+                disk_size: 200,
+                update_size,
+            };
+
+            fs.plan_deletion(&sizes, device).map(|plan| {
+                let paths: Vec<_> = plan.directories.iter().map(|&id| fs.path(id)).collect();
+
+                (paths, plan.freed)
+            })
+        };
+
+        assert_eq!(plan_for(100).unwrap(), (vec!["/y".to_owned()], 50));
+        assert_eq!(
+            plan_for(150).unwrap(),
+            (vec!["/x".to_owned(), "/z".to_owned()], 90)
+        );
+        assert_eq!(plan_for(200).unwrap().1, 140);
+        assert!(plan_for(201).is_err());
+
+        let mut report = vec![];
+        let plan = fs
+            .plan_deletion(
+                &sizes,
+                Device {
+                    disk_size: 200,
+                    update_size: 150,
+                },
+            )
+            .unwrap();
+        fs.write_plan(&mut report, &sizes, &plan).unwrap();
+        assert_eq!(
+            String::from_utf8(report).unwrap(),
+            "Space needed: 90\nWould remove /x (60, 0 directories, 1 files)\nWould remove /z (30, 1 directories, 2 files)\nWould free 90 in 2 directories\n"
+        );
+    }
+
+    #[test]
+    fn test_plan_deletion_many_directories() {
+        // Every directory holds a byte next to a subdirectory, doubling the plans to consider
+        let mut fs = VirtualFs::new();
+        let root = fs.root();
+        for index in 0..20 {
+            let directory = fs.add_directory(root, &format!("d{index:02}")).unwrap();
+            fs.add_file(directory, "byte", 1).unwrap();
+            let nested = fs.add_directory(directory, "nested").unwrap();
+            fs.add_file(nested, "file", 100).unwrap();
+        }
+        let sizes = fs.sizes();
+
+        let device = Device {
+            disk_size: 2020,
+            update_size: 2010,
+        };
+        let plan = fs.plan_deletion(&sizes, device).unwrap();
+        assert_eq!(plan.space_needed, 2010);
+        assert_eq!(plan.directories.len(), 20);
+        assert_eq!(plan.freed, 2010);
+        assert_eq!(
+            plan.directories
+                .iter()
+                .filter(|&&id| fs.node(id).name == "nested")
+                .count(),
+            10
+        );
+    }
+
+    #[test]
+    fn test_interpreter_edge_cases() {
+        let commands = parse_file(
+            "$ cd ..\n$ cd /a/b\n$ ls\n10 x\n$ pwd\n/a/b\n$ ls\n10 x\n20 y\n$ cd ../c/./d\n$ pwd\n/a/b\n$ ls\n5 z\n$ cd /a\n$ ls\ndir b\n7 c\n$ cd /\n$ echo hi\nhi\n$ ls\n$ cd a/b\n$ ls\n30 x",
+        )
+        .unwrap();
+        assert_eq!(commands[3], CommandOutput::Pwd(Some("/a/b".to_owned())));
+        assert_eq!(commands[11], CommandOutput::Unknown("echo hi".to_owned()));
+
+        let (fs, warnings) = VirtualFs::interpret(&commands);
+        assert_eq!(
+            warnings,
+            vec![
+                Warning::AboveRoot { command: 1 },
+                Warning::PwdMismatch {
+                    command: 7,
+                    printed: "/a/b".to_owned(),
+                    actual: "/a/c/d".to_owned()
+                },
+                Warning::KindConflict {
+                    command: 10,
+                    path: "/a/c".to_owned()
+                },
+                Warning::UnknownCommand {
+                    command: 12,
+                    name: "echo hi".to_owned()
+                },
+                Warning::SizeConflict {
+                    command: 15,
+                    path: "/a/b/x".to_owned(),
+                    previous: 10,
+                    listed: 30
+                },
+            ]
+        );
+
+        let b = fs.child(fs.child(fs.root(), "a").unwrap(), "b").unwrap();
+        let sizes = fs.sizes();
+        assert_eq!(sizes[b.0], 50);
+        assert_eq!(sizes[fs.root().0], 55);
+    }
+
+    #[test]
+    fn test_interpreter_unknown_directory() {
+        let commands = parse_file(
+            "$ cd /\n$ ls\n1 f\n$ cd f\n$ ls\n2 g\n$ cd x\n$ pwd\n$ ls\n3 h\n$ cd /d\n$ ls\n4 i\n",
+        )
+        .unwrap();
+
+        let (fs, warnings) = VirtualFs::interpret(&commands);
+        assert_eq!(
+            warnings,
+            vec![
+                Warning::KindConflict {
+                    command: 3,
+                    path: "/f".to_owned()
+                },
+                Warning::UnknownDirectory { command: 4 },
+                Warning::UnknownDirectory { command: 5 },
+                Warning::UnknownDirectory { command: 6 },
+                Warning::UnknownDirectory { command: 7 },
+            ]
+        );
+
+        assert_eq!(
+            fs.files().map(|id| fs.path(id)).collect::<Vec<_>>(),
+            vec!["/f", "/d/i"]
+        );
+        assert_eq!(fs.sizes()[fs.root().0], 5);
+    }
+
+    #[test]
+    fn test_json_export() {
+        let commands =
+            parse_file("$ cd /\n$ ls\ndir a\n3 \"q\\\"\n$ cd a\n$ ls\n$ cd ..\n").unwrap();
+        let fs = VirtualFs::from_commands(&commands);
+
+        assert_eq!(
+            fs.json().unwrap(),
+            "{\"name\": \"/\", \"type\": \"dir\", \"size\": 3, \"children\": [\n  {\"name\": \"\\\"q\\\\\\\"\", \"type\": \"file\", \"size\": 3},\n  {\"name\": \"a\", \"type\": \"dir\", \"size\": 0, \"children\": []}\n]}\n"
+        );
+    }
+
+    #[test]
+    fn test_transcript_round_trip() {
+        let fs = dev_fs();
+
+        let mut transcript = vec![];
+        fs.write_transcript(&mut transcript).unwrap();
+
+        let commands = parse_file(&String::from_utf8(transcript).unwrap()).unwrap();
+        let (replayed, warnings) = VirtualFs::interpret(&commands);
+        assert!(warnings.is_empty());
+        assert_eq!(replayed.json().unwrap(), fs.json().unwrap());
+    }
+
+    #[test]
+    fn test_from_directory() {
+        let root = std::env::temp_dir().join(format!("day7_test_{}", std::process::id()));
+        std::fs::create_dir_all(root.join("a/e")).unwrap();
+        std::fs::create_dir_all(root.join("ab")).unwrap();
+        std::fs::write(root.join("a/e/i"), vec![0; 584]).unwrap();
+        std::fs::write(root.join("ab/f"), vec![0; 20]).unwrap();
+        std::fs::write(root.join("b.txt"), "hello").unwrap();
+
+        let fs = VirtualFs::from_directory(&root);
+        std::fs::remove_dir_all(&root).unwrap();
+        let fs = fs.unwrap();
+
+        let mut transcript = vec![];
+        fs.write_transcript(&mut transcript).unwrap();
+        assert_eq!(
+            String::from_utf8(transcript.clone()).unwrap(),
+            "$ cd /\n$ ls\ndir a\ndir ab\n5 b.txt\n$ cd a\n$ ls\ndir e\n$ cd e\n$ ls\n584 i\n$ cd ..\n$ cd ..\n$ cd ab\n$ ls\n20 f\n$ cd ..\n"
+        );
+
+        let commands = parse_file(&String::from_utf8(transcript).unwrap()).unwrap();
+        let replayed = VirtualFs::from_commands(&commands);
+        assert_eq!(replayed, fs);
+        assert_eq!(replayed.sizes()[replayed.root().0], 609);
+    }
 }
@@ -1,198 +1,445 @@
-use std::path::PathBuf;
+use std::{
+    io::{BufWriter, Write},
+    path::PathBuf,
+    str::FromStr,
+};
 
 use anyhow::Result;
 
-pub(crate) fn solve(path: PathBuf) -> Result<()> {
+pub fn solve(path: PathBuf, heatmap: Option<Heatmap>, color: bool) -> Result<()> {
     let file = std::fs::read_to_string(path)?;
 
-    let heights: Vec<Vec<u32>> = file
-        .lines()
-        .map(|line| {
-            line.chars()
-                .map(|char| char.to_digit(10).unwrap())
-                .collect()
-        })
-        .collect();
-
-    log::trace!("heights: {heights:#?}");
+    let forest: Forest = file.parse()?;
+    log::debug!("Forest of {}x{} trees", forest.width(), forest.height());
 
-    let l_to_r_height: Vec<Vec<u32>> = heights
+    let visible_tree_count = forest
+        .visibility()
         .iter()
-        .map(|line| {
-            line.iter()
-                .fold((vec![], 0), |(mut acc, mut max), &value| {
-                    acc.push(max);
+        .filter(|visibility| visibility.is_visible())
+        .count();
+    log::info!("visible_tree_count: {visible_tree_count}");
 
-                    if value > max {
-                        max = value;
-                    }
+    let scenic_scores = forest.scenic_scores();
+    log::trace!("scenic_scores: {scenic_scores:?}");
 
-                    (acc, max)
-                })
-                .0
-        })
-        .collect();
-    log::debug!("l_to_r_height: {l_to_r_height:?}");
+    let max_scenic_score = scenic_scores.iter().max();
+    log::info!("max_scenic_score: {max_scenic_score:?}");
 
-    let r_to_l_height: Vec<Vec<u32>> = heights
-        .iter()
-        .map(|line| {
-            line.iter()
-                .rev()
-                .fold((vec![], 0), |(mut acc, mut max), &value| {
-                    acc.push(max);
-
-                    if value > max {
-                        max = value;
-                    }
+    if let Some((x, y, score)) = forest.best_treehouse(&scenic_scores) {
+        log::info!("Best treehouse location: x {x}, y {y} with a scenic score of {score}");
+    }
 
-                    (acc, max)
-                })
-                .0
-                .into_iter()
-                .rev()
-                .collect()
-        })
-        .collect();
-    log::debug!("r_to_l_height: {r_to_l_height:?}");
+    if let Some(heatmap) = heatmap {
+        let mut writer = BufWriter::new(std::io::stdout().lock());
+        forest.write_heatmap(&mut writer, &scenic_scores, heatmap, color)?;
+        writer.flush()?;
+    }
 
-    let transposed_heights = transpose(heights.clone());
-    log::trace!("transposed_heights: {transposed_heights:#?}");
+    Ok(())
+}
 
-    let t_to_b_height: Vec<Vec<u32>> = transposed_heights
-        .iter()
-        .map(|line| {
-            line.iter()
-                .fold((vec![], 0), |(mut acc, mut max), &value| {
-                    acc.push(max);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Heatmap {
+    /// Directions from which each tree is visible
+    Visibility,
+    /// Scenic score of each tree
+    Scenic,
+}
 
-                    if value > max {
-                        max = value;
-                    }
+impl Heatmap {
+    /// Background colors of the 256 color palette, from low to high scenic scores.
+    const COLORS: [u8; 12] = [17, 18, 19, 25, 31, 37, 43, 148, 184, 220, 208, 196];
+    /// Characters from low to high scenic scores, used without colors.
+    const CHARS: [char; 10] = [' ', '.', ':', '-', '=', '+', '*', '#', '%', '@'];
+}
 
-                    (acc, max)
-                })
-                .0
-        })
-        .collect();
-    log::debug!("t_to_b_height: {t_to_b_height:?}");
+/// Directions from which a tree can be seen from outside the grid.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Visibility(u8);
 
-    let b_to_t_height: Vec<Vec<u32>> = transposed_heights
-        .iter()
-        .map(|line| {
-            line.iter()
-                .rev()
-                .fold((vec![], 0), |(mut acc, mut max), &value| {
-                    acc.push(max);
-
-                    if value > max {
-                        max = value;
-                    }
+impl Visibility {
+    pub const LEFT: Self = Self(1);
+    pub const RIGHT: Self = Self(1 << 1);
+    pub const TOP: Self = Self(1 << 2);
+    pub const BOTTOM: Self = Self(1 << 3);
 
-                    (acc, max)
-                })
-                .0
-                .into_iter()
-                .rev()
-                .collect()
-        })
-        .collect();
-    log::debug!("b_to_t_height: {b_to_t_height:?}");
+    pub fn is_visible(&self) -> bool {
+        self.0 != 0
+    }
 
-    let map_size = heights.len();
-    let visible_tree_count = heights
-        .iter()
-        .enumerate()
-        .flat_map(|(idx, line)| {
-            line.iter()
-                .enumerate()
-                .map(|(idy, &value)| {
-                    idx == 0
-                        || idy == 0
-                        || idx == map_size - 1
-                        || idy == map_size - 1
-                        || l_to_r_height[idx][idy] < value
-                        || r_to_l_height[idx][idy] < value
-                        || t_to_b_height[idy][idx] < value
-                        || b_to_t_height[idy][idx] < value
-                })
-                .collect::<Vec<_>>()
-        })
-        .filter(|&value| value)
-        .count();
-    log::info!("visible_tree_count: {visible_tree_count}");
-
-    let scenic_scores = heights
-        .iter()
-        .enumerate()
-        .map(|(idy, line)| {
-            line.iter()
-                .enumerate()
-                .map(|(idx, &value)| {
-                    if idx == 0 || idy == 0 || idx == map_size - 1 || idy == map_size - 1 {
-                        return 0;
-                    }
+    pub fn contains(&self, direction: Self) -> bool {
+        self.0 & direction.0 == direction.0
+    }
 
-                    let mut l = 0;
-                    let mut r = 0;
-                    let mut u = 0;
-                    let mut d = 0;
+    fn insert(&mut self, direction: Self) {
+        self.0 |= direction.0;
+    }
+}
 
-                    for index in (0..idx).rev() {
-                        l += 1;
+/// Grid of tree heights, stored row by row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Forest {
+    width: usize,
+    height: usize,
+    heights: Vec<u8>,
+}
 
-                        if heights[idy][index] >= value {
-                            break;
-                        }
+impl Forest {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.heights[y * self.width + x]
+    }
+
+    /// Indices of the trees along each row from left to right, followed by those along
+    /// each column from top to bottom.
+    fn lines(&self) -> impl Iterator<Item = Vec<usize>> + '_ {
+        let rows = (0..self.height).map(|y| (0..self.width).map(|x| y * self.width + x).collect());
+        let columns =
+            (0..self.width).map(|x| (0..self.height).map(|y| y * self.width + x).collect());
+
+        rows.chain(columns)
+    }
+
+    /// Directions from which each tree is visible, indexed like the heights.
+    pub fn visibility(&self) -> Vec<Visibility> {
+        let mut visibility = vec![Visibility::default(); self.heights.len()];
+
+        for (index, line) in self.lines().enumerate() {
+            let (forward, backward) = if index < self.height {
+                (Visibility::LEFT, Visibility::RIGHT)
+            } else {
+                (Visibility::TOP, Visibility::BOTTOM)
+            };
+
+            for (direction, indices) in [
+                (forward, line.clone()),
+                (backward, line.iter().rev().copied().collect()),
+            ] {
+                let mut max = None;
+
+                for tree in indices {
+                    if max.is_none_or(|max| self.heights[tree] > max) {
+                        visibility[tree].insert(direction);
+                        max = Some(self.heights[tree]);
                     }
-
-                    for index in (idx + 1)..map_size {
-                        r += 1;
-
-                        if heights[idy][index] >= value {
-                            break;
-                        }
+                }
+            }
+        }
+
+        visibility
+    }
+
+    /// Product of the viewing distances in all four directions for each tree, indexed
+    /// like the heights.
+    pub fn scenic_scores(&self) -> Vec<u64> {
+        let mut scores = vec![1; self.heights.len()];
+
+        for line in self.lines() {
+            let reversed = line.iter().rev().copied().collect();
+            for indices in [line, reversed] {
+                // Positions of the trees not yet blocked by a taller or equally tall tree,
+                // their heights decreasing from the bottom of the stack to its top
+                let mut stack: Vec<usize> = vec![];
+
+                for (position, &tree) in indices.iter().enumerate() {
+                    while stack.last().is_some_and(|&blocking| {
+                        self.heights[indices[blocking]] < self.heights[tree]
+                    }) {
+                        stack.pop();
                     }
 
-                    for index in (0..idy).rev() {
-                        u += 1;
-
-                        if heights[index][idx] >= value {
-                            break;
-                        }
+                    // Either up to the blocking tree or up to the edge
+                    let distance = position - stack.last().copied().unwrap_or(0);
+                    scores[tree] *= distance as u64;
+
+                    stack.push(position);
+                }
+            }
+        }
+
+        scores
+    }
+
+    /// Position and score of the tree with the highest of the `scores`, as computed by
+    /// [`Forest::scenic_scores`], the first one in reading order winning ties.
+    pub fn best_treehouse(&self, scores: &[u64]) -> Option<(usize, usize, u64)> {
+        let best = best_index(scores)?;
+
+        Some((best % self.width, best / self.width, scores[best]))
+    }
+
+    /// Writes the grid followed by a legend. Trees show the bitmask of the directions
+    /// they are visible from as a hexadecimal digit, or their score out of `scores` as a
+    /// shade. The best treehouse location is highlighted.
+    pub fn write_heatmap<W: Write>(
+        &self,
+        writer: &mut W,
+        scores: &[u64],
+        heatmap: Heatmap,
+        color: bool,
+    ) -> Result<()> {
+        let visibility = self.visibility();
+        let max_score = scores.iter().copied().max().unwrap_or_default();
+        let best = best_index(scores);
+
+        // Square root scaling spreads the many low scores over more shades
+        let shade = |score: u64, levels: usize| -> usize {
+            if max_score == 0 {
+                return 0;
+            }
+
+            ((score as f64 / max_score as f64).sqrt() * (levels - 1) as f64).round() as usize
+        };
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = y * self.width + x;
+                let is_best = best == Some(index);
+
+                let symbol = match heatmap {
+                    Heatmap::Visibility if visibility[index].is_visible() => {
+                        char::from_digit(u32::from(visibility[index].0), 16).expect("Mask fits")
                     }
-
-                    for line in heights.iter().take(map_size).skip(idy + 1) {
-                        d += 1;
-
-                        if line[idx] >= value {
-                            break;
-                        }
+                    Heatmap::Visibility => '.',
+                    Heatmap::Scenic if color => char::from(b'0' + self.get(x, y)),
+                    Heatmap::Scenic => Heatmap::CHARS[shade(scores[index], Heatmap::CHARS.len())],
+                };
+
+                match (color, heatmap) {
+                    (false, _) if is_best => write!(writer, "O")?,
+                    (false, _) => write!(writer, "{symbol}")?,
+                    (true, _) if is_best => write!(writer, "\x1b[1;97;41m{symbol}")?,
+                    (true, Heatmap::Visibility) => {
+                        let foreground = if visibility[index].is_visible() {
+                            92
+                        } else {
+                            90
+                        };
+                        write!(writer, "\x1b[{foreground}m{symbol}")?
                     }
+                    (true, Heatmap::Scenic) => {
+                        let background =
+                            Heatmap::COLORS[shade(scores[index], Heatmap::COLORS.len())];
+                        write!(writer, "\x1b[97;48;5;{background}m{symbol}")?
+                    }
+                }
+            }
+
+            if color {
+                write!(writer, "\x1b[0m")?;
+            }
+            writeln!(writer)?;
+        }
+
+        match heatmap {
+            Heatmap::Visibility => writeln!(
+                writer,
+                "Visible from: 1 left, 2 right, 4 top, 8 bottom, summed up. Hidden: ."
+            )?,
+            Heatmap::Scenic => writeln!(writer, "Scenic scores from 0 to {max_score}")?,
+        }
+
+        if let Some(best) = best {
+            let (x, y, score) = (best % self.width, best / self.width, scores[best]);
+            let marker = if color { "highlighted" } else { "O" };
+            writeln!(
+                writer,
+                "Best treehouse ({marker}): x {x}, y {y}, score {score}"
+            )?;
+        }
+
+        Ok(())
+    }
+}
 
-                    l * r * u * d
-                })
-                .collect::<Vec<_>>()
-        })
-        .collect::<Vec<_>>();
-    log::trace!("scenic_scores: {scenic_scores:#?}");
-
-    let max_scenic_score = scenic_scores.into_iter().flatten().max();
-    log::info!("max_scenic_score: {max_scenic_score:?}");
-
-    Ok(())
+/// Index of the highest score, the first one winning ties.
+fn best_index(scores: &[u64]) -> Option<usize> {
+    (0..scores.len()).rev().max_by_key(|&index| scores[index])
 }
 
-fn transpose<T>(v: Vec<Vec<T>>) -> Vec<Vec<T>> {
-    assert!(!v.is_empty());
-    let len = v[0].len();
-    let mut iters: Vec<_> = v.into_iter().map(|n| n.into_iter()).collect();
-    (0..len)
-        .map(|_| {
-            iters
-                .iter_mut()
-                .map(|n| n.next().unwrap())
-                .collect::<Vec<T>>()
+impl FromStr for Forest {
+    type Err = anyhow::Error;
+
+    fn from_str(file: &str) -> Result<Self, Self::Err> {
+        let mut heights = vec![];
+        let mut width = None;
+        let mut height = 0;
+
+        for (index, line) in file.lines().enumerate() {
+            let row = line
+                .chars()
+                .map(|char| char.to_digit(10).map(|digit| digit as u8))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| anyhow::anyhow!("Line {}: Invalid tree height", index + 1))?;
+
+            match width {
+                None => width = Some(row.len()),
+                Some(width) if width != row.len() => anyhow::bail!(
+                    "Line {}: Expected {width} trees, found {}",
+                    index + 1,
+                    row.len()
+                ),
+                Some(_) => {}
+            }
+
+            heights.extend(row);
+            height += 1;
+        }
+
+        let width = width.unwrap_or_default();
+        if width == 0 {
+            anyhow::bail!("The forest is empty");
+        }
+
+        Ok(Self {
+            width,
+            height,
+            heights,
         })
-        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_FILE: &str = include_str!("../tasks/day8_dev.txt");
+
+    fn visible_count(forest: &Forest) -> usize {
+        forest
+            .visibility()
+            .iter()
+            .filter(|visibility| visibility.is_visible())
+            .count()
+    }
+
+    #[test]
+    fn test_square_forest() {
+        let forest: Forest = TEST_FILE.parse().unwrap();
+
+        assert_eq!(visible_count(&forest), 21);
+        assert_eq!(forest.scenic_scores().into_iter().max(), Some(8));
+        assert_eq!(forest.scenic_scores()[3 * 5 + 2], 8);
+    }
+
+    #[test]
+    fn test_rectangular_forest() {
+        let wide: Forest = "30373\n25512\n65332\n".parse().unwrap();
+        let tall: Forest = "326\n055\n753\n313\n232\n".parse().unwrap();
+
+        for forest in [&wide, &tall] {
+            assert_eq!(visible_count(forest), 14);
+            assert_eq!(forest.scenic_scores().into_iter().max(), Some(2));
+        }
+        assert_eq!((wide.width(), wide.height()), (5, 3));
+        assert_eq!(wide.get(4, 1), 2);
+        assert!(!wide.visibility()[8].is_visible());
+        assert!(wide.visibility()[7].contains(Visibility::RIGHT));
+
+        let row: Forest = "3037".parse().unwrap();
+        assert_eq!(visible_count(&row), 4);
+        assert_eq!(row.scenic_scores(), vec![0; 4]);
+
+        assert!("303\n25\n".parse::<Forest>().is_err());
+        assert!("3a3\n".parse::<Forest>().is_err());
+    }
+
+    fn naive_scenic_scores(forest: &Forest) -> Vec<u64> {
+        let mut scores = vec![];
+
+        for y in 0..forest.height() {
+            for x in 0..forest.width() {
+                let value = forest.get(x, y);
+                let distance = |trees: Vec<u8>| {
+                    let visible = trees.iter().take_while(|&&height| height < value).count();
+
+                    (visible + usize::from(visible < trees.len())) as u64
+                };
+
+                scores.push(
+                    distance((0..x).rev().map(|index| forest.get(index, y)).collect())
+                        * distance(
+                            (x + 1..forest.width())
+                                .map(|index| forest.get(index, y))
+                                .collect(),
+                        )
+                        * distance((0..y).rev().map(|index| forest.get(x, index)).collect())
+                        * distance(
+                            (y + 1..forest.height())
+                                .map(|index| forest.get(x, index))
+                                .collect(),
+                        ),
+                );
+            }
+        }
+
+        scores
+    }
+
+    #[test]
+    fn test_scenic_scores_match_naive() {
+        // Small linear congruential generator to get a reproducible forest
+        let mut state: u32 = 7;
+        let file = (0..40)
+            .map(|_| {
+                (0..65)
+                    .map(|_| {
+                        state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                        char::from(b'0' + ((state >> 16) % 10) as u8)
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let forest: Forest = file.parse().unwrap();
+
+        assert_eq!(forest.scenic_scores(), naive_scenic_scores(&forest));
+
+        let dev: Forest = TEST_FILE.parse().unwrap();
+        assert_eq!(dev.scenic_scores(), naive_scenic_scores(&dev));
+    }
+
+    #[test]
+    fn test_heatmap() {
+        let forest: Forest = TEST_FILE.parse().unwrap();
+        let scores = forest.scenic_scores();
+        assert_eq!(forest.best_treehouse(&scores), Some((2, 3, 8)));
+
+        let mut visibility = vec![];
+        forest
+            .write_heatmap(&mut visibility, &scores, Heatmap::Visibility, false)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(visibility).unwrap(),
+            "54476\n156.2\nf2.22\n1.O.f\n998fa\n\
+             Visible from: 1 left, 2 right, 4 top, 8 bottom, summed up. Hidden: .\n\
+             Best treehouse (O): x 2, y 3, score 8\n"
+        );
+
+        let mut scenic = vec![];
+        forest
+            .write_heatmap(&mut scenic, &scores, Heatmap::Scenic, false)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(scenic).unwrap(),
+            "     \n -*- \n %-+ \n -O* \n     \n\
+             Scenic scores from 0 to 8\n\
+             Best treehouse (O): x 2, y 3, score 8\n"
+        );
+
+        let mut colored = vec![];
+        forest
+            .write_heatmap(&mut colored, &scores, Heatmap::Scenic, true)
+            .unwrap();
+        assert!(String::from_utf8(colored)
+            .unwrap()
+            .contains("\x1b[1;97;41m5"));
+    }
 }
@@ -1,87 +1,441 @@
-use std::{collections::HashSet, path::PathBuf};
+use std::{
+    collections::HashSet,
+    io::{BufWriter, Write},
+    path::PathBuf,
+};
 
 use anyhow::Result;
 use chumsky::prelude::*;
 
-pub(crate) fn solve(path: PathBuf, knot_count: usize) -> Result<()> {
+pub fn solve(path: PathBuf, config: Config) -> Result<()> {
     let file = std::fs::read_to_string(path)?;
 
     let instructions = file_parser()
         .parse(file)
         .map_err(|err| anyhow::anyhow!("An error occurred while parsing the file: {err:?}"))?;
 
-    let mut pos_knots = vec![(0, 0); knot_count];
+    let rule = config.rule.build(config.max_lag)?;
+    let rope = Rope::new(config.knot_count)?;
 
-    let mut unique_tail_pos: HashSet<(i32, i32)> = HashSet::new();
+    // States are only kept for drawing frames, everything else is collected step by step
+    let mut states = vec![];
+    let mut bounds = Bounds::new(rope.knots().iter().copied());
+    let mut stats = config.stats.then(|| StatsCollector::new(&rope));
 
-    for (direction, steps) in instructions {
-        for _ in 0..steps {
-            if let Some(pos_head) = pos_knots.first_mut() {
-                *pos_head = match direction {
-                    Direction::Right => (pos_head.0 + 1, pos_head.1),
-                    Direction::Left => (pos_head.0 - 1, pos_head.1),
-                    Direction::Up => (pos_head.0, pos_head.1 + 1),
-                    Direction::Down => (pos_head.0, pos_head.1 - 1),
-                };
-            }
+    let unique_tail_pos = tail_positions(&rope, &instructions, rule.as_ref(), |index, state| {
+        bounds.extend(state.knots().iter().copied());
 
-            for i in 1..pos_knots.len() {
-                let pos_prev = pos_knots[i - 1];
-                let pos_current = &mut pos_knots[i];
-
-                if !within_bounds(&pos_prev, 1, pos_current) {
-                    // Vertical move
-                    if pos_prev.0 == pos_current.0 {
-                        if pos_prev.1 < pos_current.1 {
-                            pos_current.1 -= 1;
-                        } else {
-                            pos_current.1 += 1;
-                        }
-                    // Horizontal move
-                    } else if pos_prev.1 == pos_current.1 {
-                        if pos_prev.0 < pos_current.0 {
-                            pos_current.0 -= 1;
-                        } else {
-                            pos_current.0 += 1;
-                        }
-                    } else {
-                        // Diagonal move
-                        *pos_current =
-                            match (pos_prev.0 > pos_current.0, pos_prev.1 > pos_current.1) {
-                                (true, true) => (pos_current.0 + 1, pos_current.1 + 1),
-                                (true, false) => (pos_current.0 + 1, pos_current.1 - 1),
-                                (false, true) => (pos_current.0 - 1, pos_current.1 + 1),
-                                (false, false) => (pos_current.0 - 1, pos_current.1 - 1),
-                            };
-                    }
-                }
-            }
+        if let Some(stats) = &mut stats {
+            stats.record(state);
+        }
 
-            if let Some(pos_tail) = pos_knots.last() {
-                unique_tail_pos.insert(*pos_tail);
-            }
+        if config.frames.is_some() {
+            states.push((index, state.clone()));
         }
-    }
+    });
 
     log::debug!("unique_tail_pos: {unique_tail_pos:?}");
 
     log::info!("Unique tail positions: {}", unique_tail_pos.len());
 
+    if let Some(stats) = stats {
+        for (index, stats) in stats.stats().iter().enumerate() {
+            log::info!(
+                "Knot {}: {} unique positions, moved {} times",
+                rope.label(index),
+                stats.visited,
+                stats.moves
+            );
+        }
+    }
+
+    if config.frames.is_some() || config.visited {
+        let mut writer = BufWriter::new(std::io::stdout().lock());
+
+        if let Some(frames) = config.frames {
+            write_frames(&mut writer, &rope, &instructions, &states, frames, &bounds)?;
+        }
+
+        if config.visited {
+            writeln!(writer, "== Visited by the tail ==")?;
+            write_visited(&mut writer, &unique_tail_pos, &bounds)?;
+        }
+
+        writer.flush()?;
+    }
+
     Ok(())
 }
 
-fn within_bounds(source: &(i32, i32), size: i32, target: &(i32, i32)) -> bool {
-    (source.0 - target.0).abs() <= size && (source.1 - target.1).abs() <= size
+/// Rope to simulate and what to report about it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    pub knot_count: usize,
+    pub rule: FollowModel,
+    pub max_lag: i32,
+    /// Logs the unique positions and moves of every knot
+    pub stats: bool,
+    pub frames: Option<Frames>,
+    /// Draws the positions visited by the tail
+    pub visited: bool,
+}
+
+/// Position of a knot, `y` growing upwards.
+pub type Position = (i32, i32);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rope {
+    knots: Vec<Position>,
+}
+
+impl Rope {
+    /// Rope with all knots at the origin, the first one being the head.
+    pub fn new(knot_count: usize) -> Result<Self> {
+        if knot_count == 0 {
+            anyhow::bail!("A rope needs at least one knot");
+        }
+
+        Ok(Self {
+            knots: vec![(0, 0); knot_count],
+        })
+    }
+
+    pub fn knots(&self) -> &[Position] {
+        &self.knots
+    }
+
+    pub fn head(&self) -> Position {
+        self.knots[0]
+    }
+
+    pub fn tail(&self) -> Position {
+        self.knots[self.knots.len() - 1]
+    }
+
+    /// Moves the head by one step, every other knot following the one before it.
+    pub fn step(&mut self, direction: Direction, rule: &dyn FollowRule) {
+        let head = &mut self.knots[0];
+        *head = match direction {
+            Direction::Right => (head.0 + 1, head.1),
+            Direction::Left => (head.0 - 1, head.1),
+            Direction::Up => (head.0, head.1 + 1),
+            Direction::Down => (head.0, head.1 - 1),
+        };
+
+        for i in 1..self.knots.len() {
+            self.knots[i] = rule.follow(self.knots[i - 1], self.knots[i]);
+        }
+    }
+
+    /// Label of the knot drawn at the position, the knot closest to the head being on top.
+    fn label_at(&self, position: Position) -> Option<char> {
+        let index = self.knots.iter().position(|&knot| knot == position)?;
+
+        Some(self.label(index))
+    }
+
+    /// `H` for the head, `T` for the tail and the index for the knots in between.
+    pub fn label(&self, index: usize) -> char {
+        match index {
+            0 => 'H',
+            index if index == self.knots.len() - 1 => 'T',
+            index => char::from_digit(index as u32, 10).unwrap_or('*'),
+        }
+    }
+}
+
+/// Decides how a knot moves after the knot before it has moved.
+pub trait FollowRule {
+    fn follow(&self, leader: Position, knot: Position) -> Position;
 }
 
-#[derive(Debug, PartialEq)]
-enum Direction {
+/// Keeps knots within `max_lag` steps in both directions, moving diagonally if they are
+/// neither in the same row nor in the same column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chebyshev {
+    pub max_lag: i32,
+}
+
+impl FollowRule for Chebyshev {
+    fn follow(&self, leader: Position, knot: Position) -> Position {
+        let (dx, dy) = (leader.0 - knot.0, leader.1 - knot.1);
+        if dx.abs().max(dy.abs()) <= self.max_lag {
+            return knot;
+        }
+
+        (knot.0 + dx.signum(), knot.1 + dy.signum())
+    }
+}
+
+/// Keeps knots within `max_lag` steps along rows and columns combined, moving only along
+/// the axis with the larger distance, horizontally on ties.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Manhattan {
+    pub max_lag: i32,
+}
+
+impl FollowRule for Manhattan {
+    fn follow(&self, leader: Position, knot: Position) -> Position {
+        let (dx, dy) = (leader.0 - knot.0, leader.1 - knot.1);
+        if dx.abs() + dy.abs() <= self.max_lag {
+            return knot;
+        }
+
+        if dx.abs() >= dy.abs() {
+            (knot.0 + dx.signum(), knot.1)
+        } else {
+            (knot.0, knot.1 + dy.signum())
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum FollowModel {
+    /// Knots touch diagonally, as in the puzzle
+    Chebyshev,
+    /// Knots only touch along rows and columns
+    Manhattan,
+}
+
+impl FollowModel {
+    pub fn build(self, max_lag: i32) -> Result<Box<dyn FollowRule>> {
+        if max_lag < 1 {
+            anyhow::bail!("The maximum lag has to be at least 1");
+        }
+
+        Ok(match self {
+            FollowModel::Chebyshev => Box::new(Chebyshev { max_lag }),
+            FollowModel::Manhattan => Box::new(Manhattan { max_lag }),
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct KnotStats {
+    /// Amount of unique positions, including the start
+    pub visited: usize,
+    /// Amount of steps in which the knot moved
+    pub moves: usize,
+}
+
+/// Collects the statistics of every knot step by step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatsCollector {
+    knots: Vec<Position>,
+    visited: Vec<HashSet<Position>>,
+    moves: Vec<usize>,
+}
+
+impl StatsCollector {
+    pub fn new(rope: &Rope) -> Self {
+        Self {
+            knots: rope.knots().to_vec(),
+            visited: rope
+                .knots()
+                .iter()
+                .map(|&knot| HashSet::from([knot]))
+                .collect(),
+            moves: vec![0; rope.knots().len()],
+        }
+    }
+
+    /// Records the rope after a step.
+    pub fn record(&mut self, rope: &Rope) {
+        for (index, &knot) in rope.knots().iter().enumerate() {
+            if knot != self.knots[index] {
+                self.knots[index] = knot;
+                self.visited[index].insert(knot);
+                self.moves[index] += 1;
+            }
+        }
+    }
+
+    /// Statistics of every knot, starting at the head.
+    pub fn stats(&self) -> Vec<KnotStats> {
+        self.visited
+            .iter()
+            .zip(&self.moves)
+            .map(|(visited, &moves)| KnotStats {
+                visited: visited.len(),
+                moves,
+            })
+            .collect()
+    }
+}
+
+/// Carries out the instructions, passing the index of the instruction and the rope after
+/// every single step to `on_step`.
+pub fn simulate(
+    rope: &Rope,
+    instructions: &[(Direction, u8)],
+    rule: &dyn FollowRule,
+    mut on_step: impl FnMut(usize, &Rope),
+) {
+    let mut rope = rope.clone();
+
+    for (index, &(direction, steps)) in instructions.iter().enumerate() {
+        for _ in 0..steps {
+            rope.step(direction, rule);
+            on_step(index, &rope);
+        }
+    }
+}
+
+/// Simulates the rope like [`simulate`], returning the positions visited by the tail. The
+/// start counts as visited like in the puzzle, even if the tail never returns to it.
+pub fn tail_positions(
+    rope: &Rope,
+    instructions: &[(Direction, u8)],
+    rule: &dyn FollowRule,
+    mut on_step: impl FnMut(usize, &Rope),
+) -> HashSet<Position> {
+    let mut visited = HashSet::from([rope.tail()]);
+
+    simulate(rope, instructions, rule, |index, state| {
+        visited.insert(state.tail());
+        on_step(index, state);
+    });
+
+    visited
+}
+
+/// Collects the index of the instruction and the rope after every single step.
+pub fn collect_states(
+    rope: &Rope,
+    instructions: &[(Direction, u8)],
+    rule: &dyn FollowRule,
+) -> Vec<(usize, Rope)> {
+    let mut states = vec![];
+    simulate(rope, instructions, rule, |index, state| {
+        states.push((index, state.clone()))
+    });
+
+    states
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Frames {
+    /// Draws the rope after every step
+    Step,
+    /// Draws the rope after every instruction
+    Instruction,
+}
+
+/// Area covering every position drawn, including the start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bounds {
+    min: Position,
+    max: Position,
+}
+
+impl Bounds {
+    pub fn new(positions: impl Iterator<Item = Position>) -> Self {
+        let mut bounds = Self {
+            min: (0, 0),
+            max: (0, 0),
+        };
+        bounds.extend(positions);
+
+        bounds
+    }
+
+    /// Grows the area to cover the positions as well.
+    pub fn extend(&mut self, positions: impl Iterator<Item = Position>) {
+        for (x, y) in positions {
+            self.min = (self.min.0.min(x), self.min.1.min(y));
+            self.max = (self.max.0.max(x), self.max.1.max(y));
+        }
+    }
+
+    /// Writes one character per position, the topmost row first.
+    fn write_grid<W: Write>(
+        &self,
+        writer: &mut W,
+        symbol: impl Fn(Position) -> char,
+    ) -> Result<()> {
+        for y in (self.min.1..=self.max.1).rev() {
+            let row: String = (self.min.0..=self.max.0).map(|x| symbol((x, y))).collect();
+            writeln!(writer, "{row}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Draws the rope like the puzzle's diagrams, marking the start with `s` if uncovered.
+pub fn write_rope<W: Write>(writer: &mut W, rope: &Rope, bounds: &Bounds) -> Result<()> {
+    bounds.write_grid(writer, |position| {
+        rope.label_at(position)
+            .unwrap_or(if position == (0, 0) { 's' } else { '.' })
+    })
+}
+
+/// Draws the initial rope followed by the rope after every step or every instruction.
+pub fn write_frames<W: Write>(
+    writer: &mut W,
+    rope: &Rope,
+    instructions: &[(Direction, u8)],
+    states: &[(usize, Rope)],
+    frames: Frames,
+    bounds: &Bounds,
+) -> Result<()> {
+    writeln!(writer, "== Initial State ==\n")?;
+    write_rope(writer, rope, bounds)?;
+    writeln!(writer)?;
+
+    for (position, (index, state)) in states.iter().enumerate() {
+        let next_index = states.get(position + 1).map(|(index, _)| *index);
+        let first_step = position == 0 || states[position - 1].0 != *index;
+        let last_step = next_index != Some(*index);
+
+        if first_step {
+            let (direction, steps) = instructions[*index];
+            writeln!(writer, "== {direction} {steps} ==\n")?;
+        }
+
+        if frames == Frames::Step || last_step {
+            write_rope(writer, state, bounds)?;
+            writeln!(writer)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Draws the positions visited by the tail with `#`, and the start with `s`.
+pub fn write_visited<W: Write>(
+    writer: &mut W,
+    visited: &HashSet<Position>,
+    bounds: &Bounds,
+) -> Result<()> {
+    bounds.write_grid(writer, |position| match position {
+        (0, 0) => 's',
+        position if visited.contains(&position) => '#',
+        _ => '.',
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
     Right,
     Left,
     Up,
     Down,
 }
 
+impl std::fmt::Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symbol = match self {
+            Direction::Right => 'R',
+            Direction::Left => 'L',
+            Direction::Up => 'U',
+            Direction::Down => 'D',
+        };
+
+        write!(f, "{symbol}")
+    }
+}
+
 fn file_parser() -> impl Parser<char, Vec<(Direction, u8)>, Error = Simple<char>> {
     (just('R').padded().map(|_| Direction::Right))
         .or(just('L').padded().map(|_| Direction::Left))
@@ -117,4 +471,158 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_rendering() {
+        let instructions = file_parser().parse(FILE).unwrap();
+        let rope = Rope::new(2).unwrap();
+        let states = collect_states(&rope, &instructions, &Chebyshev { max_lag: 1 });
+        let bounds = Bounds::new(
+            states
+                .iter()
+                .flat_map(|(_, state)| state.knots().iter().copied()),
+        );
+
+        let mut frames = vec![];
+        write_frames(
+            &mut frames,
+            &rope,
+            &instructions[..1],
+            &states[..4],
+            Frames::Instruction,
+            &bounds,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(frames).unwrap(),
+            "== Initial State ==\n\n......\n......\n......\n......\nH.....\n\n\
+             == R 4 ==\n\n......\n......\n......\n......\ns..TH.\n\n"
+        );
+
+        let visited: HashSet<_> = states.iter().map(|(_, state)| state.tail()).collect();
+        let mut map = vec![];
+        write_visited(&mut map, &visited, &bounds).unwrap();
+        assert_eq!(
+            String::from_utf8(map).unwrap(),
+            "..##..\n...##.\n.####.\n....#.\ns###..\n"
+        );
+        assert_eq!(visited.len(), 13);
+
+        let mut steps = vec![];
+        write_frames(
+            &mut steps,
+            &rope,
+            &instructions,
+            &states,
+            Frames::Step,
+            &bounds,
+        )
+        .unwrap();
+        let steps = String::from_utf8(steps).unwrap();
+        assert_eq!(steps.matches("== ").count(), instructions.len() + 1);
+        assert_eq!(
+            steps.lines().filter(|line| line.len() == 6).count(),
+            (states.len() + 1) * 5
+        );
+    }
+
+    #[test]
+    fn test_long_rope() {
+        let instructions = file_parser()
+            .parse(include_str!("../tasks/day9_dev_2.txt"))
+            .unwrap();
+        let rope = Rope::new(10).unwrap();
+        let states = collect_states(&rope, &instructions[..1], &Chebyshev { max_lag: 1 });
+
+        let rope = &states[4].1;
+        let mut frame = vec![];
+        write_rope(&mut frame, rope, &Bounds::new(rope.knots().iter().copied())).unwrap();
+        assert_eq!(String::from_utf8(frame).unwrap(), "54321H\n");
+    }
+
+    #[test]
+    fn test_tail_positions() {
+        let instructions = file_parser().parse(FILE).unwrap();
+        let rule = Chebyshev { max_lag: 1 };
+
+        let mut steps = 0;
+        let visited = tail_positions(&Rope::new(2).unwrap(), &instructions, &rule, |_, _| {
+            steps += 1
+        });
+        assert_eq!(visited.len(), 13);
+        assert_eq!(steps, 24);
+
+        // A single knot moving away still visited the start
+        let single = Rope::new(1).unwrap();
+        let visited = tail_positions(&single, &[(Direction::Right, 2)], &rule, |_, _| {});
+        assert_eq!(visited, HashSet::from([(0, 0), (1, 0), (2, 0)]));
+        assert_eq!(tail_positions(&single, &[], &rule, |_, _| {}).len(), 1);
+    }
+
+    #[test]
+    fn test_follow_rules() {
+        let chebyshev = Chebyshev { max_lag: 1 };
+        assert_eq!(chebyshev.follow((1, 1), (0, 0)), (0, 0));
+        assert_eq!(chebyshev.follow((2, 1), (0, 0)), (1, 1));
+
+        let manhattan = Manhattan { max_lag: 1 };
+        assert_eq!(manhattan.follow((1, 1), (0, 0)), (1, 0));
+        assert_eq!(manhattan.follow((1, 2), (0, 0)), (0, 1));
+        assert_eq!(manhattan.follow((0, -1), (0, 0)), (0, 0));
+
+        let lagging = Chebyshev { max_lag: 2 };
+        assert_eq!(lagging.follow((2, -2), (0, 0)), (0, 0));
+        assert_eq!(lagging.follow((3, 1), (0, 0)), (1, 1));
+
+        assert!(FollowModel::Manhattan.build(0).is_err());
+    }
+
+    fn knot_stats(
+        rope: &Rope,
+        instructions: &[(Direction, u8)],
+        rule: &dyn FollowRule,
+    ) -> Vec<KnotStats> {
+        let mut stats = StatsCollector::new(rope);
+        simulate(rope, instructions, rule, |_, state| stats.record(state));
+
+        stats.stats()
+    }
+
+    #[test]
+    fn test_knot_stats() {
+        let instructions = file_parser()
+            .parse(include_str!("../tasks/day9_dev_2.txt"))
+            .unwrap();
+        let rope = Rope::new(10).unwrap();
+
+        let stats = knot_stats(&rope, &instructions, &Chebyshev { max_lag: 1 });
+        assert_eq!(stats.len(), 10);
+        assert_eq!(stats[9].visited, 36);
+        assert_eq!(stats[0].moves, 96);
+        assert!(stats.windows(2).all(|pair| pair[0].moves >= pair[1].moves));
+
+        let stats = knot_stats(&rope, &instructions, &Chebyshev { max_lag: 2 });
+        assert!(stats[9].visited < 36);
+
+        let rope = Rope::new(3).unwrap();
+        let states = collect_states(&rope, &[(Direction::Right, 4)], &Manhattan { max_lag: 1 });
+        assert_eq!(states[3].1.knots(), &[(4, 0), (3, 0), (2, 0)]);
+        assert_eq!(
+            knot_stats(&rope, &[(Direction::Right, 4)], &Manhattan { max_lag: 1 }),
+            vec![
+                KnotStats {
+                    visited: 5,
+                    moves: 4
+                },
+                KnotStats {
+                    visited: 4,
+                    moves: 3
+                },
+                KnotStats {
+                    visited: 3,
+                    moves: 2
+                },
+            ]
+        );
+    }
 }
@@ -0,0 +1,274 @@
+use std::{fmt::Display, ops::RangeInclusive, str::FromStr};
+
+use anyhow::Result;
+
+/// Non-empty, inclusive range of sections, e.g. `2-4` covering the sections 2, 3 and 4.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Interval {
+    start: u32,
+    end: u32,
+}
+
+impl Interval {
+    pub fn new(start: u32, end: u32) -> Result<Self> {
+        if start > end {
+            anyhow::bail!("Interval start {start} is greater than its end {end}");
+        }
+
+        Ok(Self { start, end })
+    }
+
+    pub fn start(&self) -> u32 {
+        self.start
+    }
+
+    pub fn end(&self) -> u32 {
+        self.end
+    }
+
+    /// Amount of sections covered by the interval.
+    pub fn size(&self) -> u64 {
+        u64::from(self.end - self.start) + 1
+    }
+
+    pub fn contains(&self, other: &Self) -> bool {
+        self.start <= other.start && self.end >= other.end
+    }
+
+    pub fn contains_section(&self, section: u32) -> bool {
+        self.start <= section && section <= self.end
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool {
+        !(self.end < other.start || other.end < self.start)
+    }
+
+    /// Whether the intervals overlap or directly follow each other.
+    pub fn touches(&self, other: &Self) -> bool {
+        !(self.end.saturating_add(1) < other.start || other.end.saturating_add(1) < self.start)
+    }
+
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        self.overlaps(other).then(|| Self {
+            start: self.start.max(other.start),
+            end: self.end.min(other.end),
+        })
+    }
+
+    pub fn union(&self, other: &Self) -> IntervalSet {
+        IntervalSet::from_iter([*self, *other])
+    }
+}
+
+impl FromStr for Interval {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let Some((start, end)) = value.split_once('-') else {
+            anyhow::bail!("Could not split interval {value:?}");
+        };
+
+        Self::new(start.trim().parse()?, end.trim().parse()?)
+    }
+}
+
+impl Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+impl TryFrom<RangeInclusive<u32>> for Interval {
+    type Error = anyhow::Error;
+
+    fn try_from(range: RangeInclusive<u32>) -> Result<Self, Self::Error> {
+        Self::new(*range.start(), *range.end())
+    }
+}
+
+impl From<Interval> for RangeInclusive<u32> {
+    fn from(interval: Interval) -> Self {
+        interval.start..=interval.end
+    }
+}
+
+/// Normalized set of sections, stored as sorted intervals which neither overlap nor touch.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct IntervalSet {
+    intervals: Vec<Interval>,
+}
+
+impl IntervalSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, interval: Interval) {
+        let first = self
+            .intervals
+            .partition_point(|existing| existing.end.saturating_add(1) < interval.start);
+        let last = first
+            + self.intervals[first..]
+                .iter()
+                .take_while(|existing| existing.touches(&interval))
+                .count();
+
+        let merged = self.intervals[first..last]
+            .iter()
+            .fold(interval, |merged, existing| Interval {
+                start: merged.start.min(existing.start),
+                end: merged.end.max(existing.end),
+            });
+
+        self.intervals.splice(first..last, [merged]);
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut union = self.clone();
+        union.extend(other.iter().copied());
+
+        union
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut intervals = vec![];
+        let (mut a, mut b) = (
+            self.intervals.iter().peekable(),
+            other.intervals.iter().peekable(),
+        );
+
+        while let (Some(first), Some(second)) = (a.peek(), b.peek()) {
+            if let Some(intersection) = first.intersection(second) {
+                intervals.push(intersection);
+            }
+
+            if first.end < second.end {
+                a.next();
+            } else {
+                b.next();
+            }
+        }
+
+        Self { intervals }
+    }
+
+    /// Sections between the lowest and the highest covered section which are not covered.
+    pub fn gaps(&self) -> Self {
+        let intervals = self
+            .intervals
+            .windows(2)
+            .map(|pair| Interval {
+                start: pair[0].end + 1,
+                end: pair[1].start - 1,
+            })
+            .collect();
+
+        Self { intervals }
+    }
+
+    /// Total amount of sections covered.
+    pub fn coverage(&self) -> u64 {
+        self.intervals.iter().map(Interval::size).sum()
+    }
+
+    pub fn contains_section(&self, section: u32) -> bool {
+        let index = self
+            .intervals
+            .partition_point(|interval| interval.end < section);
+
+        self.intervals
+            .get(index)
+            .is_some_and(|interval| interval.contains_section(section))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Interval> {
+        self.intervals.iter()
+    }
+}
+
+impl Extend<Interval> for IntervalSet {
+    fn extend<T: IntoIterator<Item = Interval>>(&mut self, iter: T) {
+        for interval in iter {
+            self.insert(interval);
+        }
+    }
+}
+
+impl FromIterator<Interval> for IntervalSet {
+    fn from_iter<T: IntoIterator<Item = Interval>>(iter: T) -> Self {
+        let mut set = Self::new();
+        set.extend(iter);
+
+        set
+    }
+}
+
+impl Display for IntervalSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, interval) in self.intervals.iter().enumerate() {
+            if index > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{interval}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interval(start: u32, end: u32) -> Interval {
+        Interval::new(start, end).unwrap()
+    }
+
+    #[test]
+    fn test_interval_parsing() {
+        assert_eq!("2-4".parse::<Interval>().unwrap(), interval(2, 4));
+        assert_eq!("300-1000".parse::<Interval>().unwrap(), interval(300, 1000));
+        assert!("4-2".parse::<Interval>().is_err());
+        assert!("4".parse::<Interval>().is_err());
+    }
+
+    #[test]
+    fn test_interval_operations() {
+        assert!(interval(2, 8).contains(&interval(3, 7)));
+        assert!(!interval(3, 7).contains(&interval(2, 8)));
+        assert!(interval(5, 7).overlaps(&interval(7, 9)));
+        assert!(!interval(2, 4).overlaps(&interval(6, 8)));
+        assert_eq!(
+            interval(2, 6).intersection(&interval(4, 8)),
+            Some(interval(4, 6))
+        );
+        assert_eq!(interval(2, 3).intersection(&interval(4, 5)), None);
+        assert_eq!(interval(2, 3).union(&interval(4, 5)).to_string(), "2-5");
+        assert_eq!(interval(2, 3).union(&interval(5, 5)).to_string(), "2-3,5-5");
+    }
+
+    #[test]
+    fn test_interval_set() {
+        let set = IntervalSet::from_iter([
+            interval(10, 12),
+            interval(1, 2),
+            interval(5, 6),
+            interval(4, 4),
+            interval(11, 20),
+        ]);
+
+        assert_eq!(set.to_string(), "1-2,4-6,10-20");
+        assert_eq!(set.coverage(), 16);
+        assert_eq!(set.gaps().to_string(), "3-3,7-9");
+        assert!(set.contains_section(15));
+        assert!(!set.contains_section(8));
+
+        let other = IntervalSet::from_iter([interval(2, 5), interval(18, 30)]);
+        assert_eq!(set.intersection(&other).to_string(), "2-2,4-5,18-20");
+        assert_eq!(set.union(&other).to_string(), "1-6,10-30");
+    }
+}
@@ -0,0 +1,22 @@
+pub mod day1;
+pub mod day10;
+// The original tests are kept as they were written
+#[cfg_attr(test, allow(clippy::needless_borrow))]
+pub mod day11;
+pub mod day12;
+#[cfg_attr(test, allow(clippy::nonminimal_bool))]
+pub mod day13;
+pub mod day14;
+pub mod day15;
+pub mod day16;
+pub mod day17;
+pub mod day18;
+pub mod day2;
+pub mod day3;
+pub mod day4;
+pub mod day5;
+pub mod day6;
+pub mod day7;
+pub mod day8;
+pub mod day9;
+pub mod interval;
@@ -1,24 +1,9 @@
-mod day1;
-mod day10;
-mod day11;
-mod day12;
-mod day13;
-mod day14;
-mod day15;
-mod day16;
-mod day17;
-mod day18;
-mod day2;
-mod day3;
-mod day4;
-mod day5;
-mod day6;
-mod day7;
-mod day8;
-mod day9;
-
-use std::path::PathBuf;
+use std::{io::IsTerminal, path::PathBuf};
 
+use aoc2022::{
+    day1, day10, day11, day12, day13, day14, day15, day16, day17, day18, day2, day3, day4, day5,
+    day6, day7, day8, day9,
+};
 use clap::{Parser, Subcommand};
 use clap_verbosity_flag::InfoLevel;
 
@@ -37,30 +22,233 @@ enum Command {
     Day1A { path: PathBuf },
     /// Completes day 1 task B
     Day1B { path: PathBuf },
+    /// Completes day 1 for any amount of elves and computes calorie statistics
+    Day1 {
+        path: PathBuf,
+        /// Amount of elves carrying the most calories to sum up
+        #[clap(long, short, default_value_t = 3)]
+        top: usize,
+        /// Statistics to compute over the calories carried by each elf
+        #[clap(long, short, value_enum)]
+        stats: Vec<day1::Statistic>,
+        /// Percentile reported by the percentile statistic
+        #[clap(long, short, default_value_t = 90.0)]
+        percentile: f64,
+    },
+    /// Reports the elves carrying the most calories along with their items
+    Day1Report {
+        path: PathBuf,
+        /// Amount of elves to report
+        #[clap(long, short, default_value_t = 3)]
+        top: usize,
+        /// Output format of the report
+        #[clap(long, short, value_enum, default_value_t = day1::ReportFormat::Text)]
+        format: day1::ReportFormat,
+    },
     /// Completes day 2 task A
-    Day2A { path: PathBuf },
+    Day2A {
+        path: PathBuf,
+        /// File describing the shapes, symbols and scores of the game
+        #[clap(long, short)]
+        rules: Option<PathBuf>,
+    },
     /// Completes day 2 task B
-    Day2B { path: PathBuf },
+    Day2B {
+        path: PathBuf,
+        /// File describing the shapes, symbols and scores of the game
+        #[clap(long, short)]
+        rules: Option<PathBuf>,
+    },
+    /// Analyzes the day 2 strategy guide and searches for the intended response mapping
+    Day2Analysis {
+        path: PathBuf,
+        /// File describing the shapes, symbols and scores of the game
+        #[clap(long, short)]
+        rules: Option<PathBuf>,
+    },
     /// Completes day 3 task A
     Day3A { path: PathBuf },
     /// Completes day 3 task B
-    Day3B { path: PathBuf },
+    Day3B {
+        path: PathBuf,
+        /// Amount of rucksacks sharing a badge
+        #[clap(long, short, default_value_t = 3)]
+        group_size: usize,
+    },
+    /// Validates the day 3 rucksacks and groups, reporting every violation
+    Day3Validate {
+        path: PathBuf,
+        /// Amount of rucksacks sharing a badge
+        #[clap(long, short, default_value_t = 3)]
+        group_size: usize,
+    },
     /// Completes day 4 tasks
-    Day4 { path: PathBuf },
+    Day4 {
+        path: PathBuf,
+        /// Prints the conflict matrix of the assignments on every line
+        #[clap(long, short, action)]
+        matrix: bool,
+    },
     /// Completes day 5 task A
-    Day5A { path: PathBuf },
+    Day5A {
+        path: PathBuf,
+        /// How to proceed once an illegal move is encountered
+        #[clap(long, short, value_enum, default_value_t = day5::IllegalMovePolicy::Stop)]
+        on_illegal_move: day5::IllegalMovePolicy,
+        /// Prints the stacks after the given amount of moves
+        #[clap(long, short)]
+        step: Option<usize>,
+        /// Exports the stacks after every move to the given file
+        #[clap(long, short)]
+        trace: Option<PathBuf>,
+    },
     /// Completes day 5 task B
-    Day5B { path: PathBuf },
+    Day5B {
+        path: PathBuf,
+        /// How to proceed once an illegal move is encountered
+        #[clap(long, short, value_enum, default_value_t = day5::IllegalMovePolicy::Stop)]
+        on_illegal_move: day5::IllegalMovePolicy,
+        /// Prints the stacks after the given amount of moves
+        #[clap(long, short)]
+        step: Option<usize>,
+        /// Exports the stacks after every move to the given file
+        #[clap(long, short)]
+        trace: Option<PathBuf>,
+    },
+    /// Completes day 5 with a selectable crane model
+    Day5 {
+        path: PathBuf,
+        /// Crane model rearranging the crates
+        #[clap(long, short, value_enum, default_value_t = day5::CraneModel::CrateMover9001)]
+        crane: day5::CraneModel,
+        /// Maximum amount of crates the limited crane moves at once
+        #[clap(long)]
+        capacity: Option<usize>,
+        /// How to proceed once an illegal move is encountered
+        #[clap(long, short, value_enum, default_value_t = day5::IllegalMovePolicy::Stop)]
+        on_illegal_move: day5::IllegalMovePolicy,
+        /// Prints the stacks after the given amount of moves
+        #[clap(long, short)]
+        step: Option<usize>,
+        /// Exports the stacks after every move to the given file
+        #[clap(long, short)]
+        trace: Option<PathBuf>,
+        /// Writes the stacks after the given step, followed by the remaining moves, as a
+        /// new puzzle to the given file
+        #[clap(long, short)]
+        export: Option<PathBuf>,
+    },
     /// Completes day 6 task A
-    Day6A { path: PathBuf },
+    Day6A {
+        path: PathBuf,
+        /// Lists every marker instead of only the first one
+        #[clap(long, short)]
+        all: bool,
+    },
     /// Completes day 6 task B
-    Day6B { path: PathBuf },
+    Day6B {
+        path: PathBuf,
+        /// Lists every marker instead of only the first one
+        #[clap(long, short)]
+        all: bool,
+    },
+    /// Decodes a day 6 datastream from a file, a TCP connection or stdin
+    Day6Stream {
+        /// Reads from stdin if neither a path nor an address to listen on is given
+        path: Option<PathBuf>,
+        /// Address to accept a single connection on, e.g. 127.0.0.1:6000
+        #[clap(long, short)]
+        listen: Option<String>,
+        /// Size of the start-of-packet marker
+        #[clap(long, short, default_value_t = 4)]
+        packet_size: usize,
+        /// Size of the start-of-message marker
+        #[clap(long, short, default_value_t = 14)]
+        message_size: usize,
+    },
     /// Completes day 7
-    Day7 { path: PathBuf },
+    Day7 {
+        path: PathBuf,
+        /// Largest size of the directories summed up in task A
+        #[clap(long, default_value_t = 100000)]
+        max_size: usize,
+        /// Total size of the disk
+        #[clap(long, default_value_t = day7::DISK_SIZE)]
+        disk_size: usize,
+        /// Space required by the update
+        #[clap(long, default_value_t = day7::UPDATE_SIZE)]
+        update_size: usize,
+    },
+    /// Plans which directories to delete to make room for the day 7 update
+    Day7Plan {
+        path: PathBuf,
+        /// Total size of the disk
+        #[clap(long, default_value_t = day7::DISK_SIZE)]
+        disk_size: usize,
+        /// Space required by the update
+        #[clap(long, default_value_t = day7::UPDATE_SIZE)]
+        update_size: usize,
+    },
+    /// Exports the filesystem reconstructed from the day 7 transcript as JSON
+    Day7Export {
+        path: PathBuf,
+        /// File to write the JSON to instead of stdout
+        #[clap(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Generates a day 7 transcript exploring a local directory
+    Day7Transcript {
+        directory: PathBuf,
+        /// File to write the transcript to instead of stdout
+        #[clap(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Reports the filesystem reconstructed from the day 7 transcript
+    Day7Report {
+        path: PathBuf,
+        /// Prints the whole tree, leaving out nodes smaller than the minimum size
+        #[clap(long, short)]
+        tree: bool,
+        /// Lists the given amount of largest directories and files
+        #[clap(long, short = 'n')]
+        largest: Option<usize>,
+        /// Only reports nodes at least this large
+        #[clap(long)]
+        min_size: Option<usize>,
+        /// Only reports nodes at most this large
+        #[clap(long)]
+        max_size: Option<usize>,
+    },
     /// Completes day 8
-    Day8 { path: PathBuf },
+    Day8 {
+        path: PathBuf,
+        /// Prints a heatmap of the forest
+        #[clap(long, short = 'm', value_enum)]
+        heatmap: Option<day8::Heatmap>,
+        /// Prints the heatmap without ANSI colors, as when stdout is not a terminal
+        #[clap(long)]
+        no_color: bool,
+    },
     /// Completes day 9
-    Day9 { knot_count: usize, path: PathBuf },
+    Day9 {
+        knot_count: usize,
+        path: PathBuf,
+        /// Rule by which each knot follows the one before it
+        #[clap(long, short, value_enum, default_value_t = day9::FollowModel::Chebyshev)]
+        rule: day9::FollowModel,
+        /// Distance a knot may lag behind the one before it
+        #[clap(long, short = 'l', default_value_t = 1)]
+        max_lag: i32,
+        /// Logs the unique positions and moves of every knot
+        #[clap(long, short)]
+        stats: bool,
+        /// Draws the rope after every step or every instruction
+        #[clap(long, short, value_enum)]
+        frames: Option<day9::Frames>,
+        /// Draws the positions visited by the tail
+        #[clap(long)]
+        visited: bool,
+    },
     /// Completes day 10
     Day10 { path: PathBuf },
     /// Completes day 11
@@ -107,18 +295,130 @@ fn main() {
     if let Err(err) = match cli.command {
         Command::Day1A { path } => day1::solve_a(path),
         Command::Day1B { path } => day1::solve_b(path),
-        Command::Day2A { path } => day2::solve(path, false),
-        Command::Day2B { path } => day2::solve(path, true),
+        Command::Day1 {
+            path,
+            top,
+            stats,
+            percentile,
+        } => day1::solve(path, top, &stats, percentile),
+        Command::Day1Report { path, top, format } => day1::solve_report(path, top, format),
+        Command::Day2A { path, rules } => day2::solve(path, false, rules),
+        Command::Day2B { path, rules } => day2::solve(path, true, rules),
+        Command::Day2Analysis { path, rules } => day2::solve_analysis(path, rules),
         Command::Day3A { path } => day3::solve(path),
-        Command::Day3B { path } => day3::solve_b(path),
-        Command::Day4 { path } => day4::solve(path),
-        Command::Day5A { path } => day5::solve(path, true),
-        Command::Day5B { path } => day5::solve(path, false),
-        Command::Day6A { path } => day6::solve(path, 4),
-        Command::Day6B { path } => day6::solve(path, 14),
-        Command::Day7 { path } => day7::solve(path),
-        Command::Day8 { path } => day8::solve(path),
-        Command::Day9 { path, knot_count } => day9::solve(path, knot_count),
+        Command::Day3B { path, group_size } => day3::solve_b(path, group_size),
+        Command::Day3Validate { path, group_size } => day3::solve_validate(path, group_size),
+        Command::Day4 { path, matrix } => day4::solve(path, matrix),
+        Command::Day5A {
+            path,
+            on_illegal_move,
+            step,
+            trace,
+        } => day5::solve(
+            path,
+            day5::CraneModel::CrateMover9000,
+            None,
+            on_illegal_move,
+            step,
+            trace,
+            None,
+        ),
+        Command::Day5B {
+            path,
+            on_illegal_move,
+            step,
+            trace,
+        } => day5::solve(
+            path,
+            day5::CraneModel::CrateMover9001,
+            None,
+            on_illegal_move,
+            step,
+            trace,
+            None,
+        ),
+        Command::Day5 {
+            path,
+            crane,
+            capacity,
+            on_illegal_move,
+            step,
+            trace,
+            export,
+        } => day5::solve(path, crane, capacity, on_illegal_move, step, trace, export),
+        Command::Day6A { path, all } => day6::solve(path, 4, all),
+        Command::Day6B { path, all } => day6::solve(path, 14, all),
+        Command::Day6Stream {
+            path,
+            listen,
+            packet_size,
+            message_size,
+        } => day6::solve_stream(path, listen, packet_size, message_size),
+        Command::Day7 {
+            path,
+            max_size,
+            disk_size,
+            update_size,
+        } => day7::solve(
+            path,
+            max_size,
+            day7::Device {
+                disk_size,
+                update_size,
+            },
+        ),
+        Command::Day7Plan {
+            path,
+            disk_size,
+            update_size,
+        } => day7::solve_plan(
+            path,
+            day7::Device {
+                disk_size,
+                update_size,
+            },
+        ),
+        Command::Day7Export { path, output } => day7::solve_export(path, output),
+        Command::Day7Transcript { directory, output } => day7::solve_transcript(directory, output),
+        Command::Day7Report {
+            path,
+            tree,
+            largest,
+            min_size,
+            max_size,
+        } => day7::solve_report(
+            path,
+            tree,
+            largest,
+            day7::SizeFilter {
+                min: min_size,
+                max: max_size,
+            },
+        ),
+        Command::Day8 {
+            path,
+            heatmap,
+            no_color,
+        } => day8::solve(path, heatmap, !no_color && std::io::stdout().is_terminal()),
+        Command::Day9 {
+            path,
+            knot_count,
+            rule,
+            max_lag,
+            stats,
+            frames,
+            visited,
+        } => day9::solve(
+            path,
+            day9::Config {
+                knot_count,
+                rule,
+                max_lag,
+                stats,
+                frames,
+                visited,
+            },
+        ),
         Command::Day10 { path } => day10::solve(path),
         Command::Day11 {
             path,